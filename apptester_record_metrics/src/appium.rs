@@ -0,0 +1,220 @@
+use std::{collections::HashMap, time::Duration};
+
+use reqwest::{
+    blocking::{Client, RequestBuilder},
+    header::CONTENT_TYPE,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{error::RecorderError, schema::DataType};
+
+/// The requests the recorder makes of an Appium server.
+pub struct Appium {
+    client: Client,
+    url: String,
+}
+
+/// Every W3C WebDriver reply wraps its result, or an error, in `value`.
+#[derive(Debug, Deserialize)]
+struct Response {
+    value: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorReply {
+    error: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// A session from `GET /sessions`.
+#[derive(Debug, Deserialize)]
+pub struct Session {
+    pub id: String,
+    #[serde(default)]
+    pub capabilities: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewSession {
+    session_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PerformanceDataRequest<'a> {
+    package_name: &'a str,
+    data_type: &'a str,
+}
+
+/// Errors that mean the session is gone or never existed.
+const SESSION_ERRORS: [&str; 2] = ["invalid session id", "session not created"];
+
+impl Appium {
+    pub fn new(client: Client, url: String) -> Self {
+        Self { client, url }
+    }
+
+    pub fn sessions(&self) -> Result<Vec<Session>, RecorderError> {
+        self.send(
+            self.client.get(format!("{}/sessions", self.url)),
+            "GET /sessions",
+        )
+    }
+
+    /// Creates a session from a `{"capabilities": ...}` request, returning its id.
+    pub fn create_session(&self, body: &Value, timeout: Duration) -> Result<String, RecorderError> {
+        let new_session: NewSession = self.send(
+            self.client
+                .post(format!("{}/session", self.url))
+                .timeout(timeout)
+                .body(body.to_string()),
+            "POST /session",
+        )?;
+        Ok(new_session.session_id)
+    }
+
+    /// Whether the session exists and answers commands within `timeout`, asked
+    /// with W3C Get Timeouts since Appium 2 deprecates `GET /session/{id}`.
+    pub fn session_ready(&self, session_id: &str, timeout: Duration) -> bool {
+        self.send::<Value>(
+            self.client
+                .get(format!("{}/session/{session_id}/timeouts", self.url))
+                .timeout(timeout),
+            "GET /session/timeouts",
+        )
+        .is_ok()
+    }
+
+    /// Fetches one data type's fields for the app under test.
+    pub fn performance_data(
+        &self,
+        session_id: &str,
+        package_name: &str,
+        data_type: DataType,
+    ) -> Result<HashMap<String, String>, RecorderError> {
+        let request = PerformanceDataRequest {
+            package_name,
+            data_type: data_type.name(),
+        };
+        let body = serde_json::to_string(&request)
+            .map_err(|err| RecorderError::Protocol(err.to_string()))?;
+        let endpoint = format!("getPerformanceData ({})", data_type.name());
+        let table: Option<Vec<Vec<Value>>> = self.send(
+            self.client
+                .post(format!(
+                    "{}/session/{session_id}/appium/getPerformanceData",
+                    self.url
+                ))
+                .body(body),
+            &endpoint,
+        )?;
+
+        match table {
+            Some(table) => vals_from_arrays(table, &endpoint),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// Sends `request` and reads the `value` of the reply as `T`, turning an
+    /// error reply into the matching [`RecorderError`].
+    fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        endpoint: &str,
+    ) -> Result<T, RecorderError> {
+        let body = request
+            .header(CONTENT_TYPE, "application/json")
+            .send()?
+            .text()?;
+        let unexpected =
+            |err: serde_json::Error| RecorderError::Protocol(format!("{endpoint}: {err}"));
+
+        let response: Response = serde_json::from_str(&body).map_err(unexpected)?;
+        if response.value.get("error").is_some() {
+            let reply: ErrorReply = serde_json::from_value(response.value).map_err(unexpected)?;
+            let message = format!("{endpoint}: {}: {}", reply.error, reply.message);
+            return Err(match SESSION_ERRORS.contains(&reply.error.as_str()) {
+                true => RecorderError::Session(message),
+                false => RecorderError::Protocol(message),
+            });
+        }
+
+        serde_json::from_value(response.value).map_err(unexpected)
+    }
+}
+
+/// Reads the device's table of field names (first row) and values (second row).
+/// A value the device doesn't have is `null` and read as empty.
+fn vals_from_arrays(
+    arrays: Vec<Vec<Value>>,
+    endpoint: &str,
+) -> Result<HashMap<String, String>, RecorderError> {
+    let (names, vals) = match &arrays[..] {
+        [names, vals, ..] => (names, vals),
+        _ => {
+            return Err(RecorderError::Protocol(format!(
+                "{endpoint}: expected a row of field names and a row of values, got {} rows",
+                arrays.len()
+            )))
+        }
+    };
+
+    let mut val_map = HashMap::new();
+    for (name, val) in names.iter().zip(vals) {
+        let name = match name {
+            Value::String(name) => name.to_owned(),
+            _ => {
+                return Err(RecorderError::Protocol(format!(
+                    "{endpoint}: field name {name} isn't a string"
+                )))
+            }
+        };
+        let val = match val {
+            Value::String(val) => val.to_owned(),
+            Value::Null => String::new(),
+            val => val.to_string(),
+        };
+        val_map.insert(name, val);
+    }
+
+    Ok(val_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::vals_from_arrays;
+    use crate::error::RecorderError;
+
+    fn table(value: Value) -> Vec<Vec<Value>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn vals_from_arrays_reads_names_and_values_or_reports_bad_tables() {
+        let vals = vals_from_arrays(
+            table(json!([
+                ["totalPss", "nativeRss", "user"],
+                ["100", null, 12]
+            ])),
+            "test",
+        )
+        .unwrap();
+        assert_eq!(vals["totalPss"], "100");
+        assert_eq!(vals["nativeRss"], "");
+        assert_eq!(vals["user"], "12");
+
+        assert!(matches!(
+            vals_from_arrays(table(json!([["totalPss"]])), "test"),
+            Err(RecorderError::Protocol(_))
+        ));
+        assert!(matches!(
+            vals_from_arrays(table(json!([[1], ["100"]])), "test"),
+            Err(RecorderError::Protocol(_))
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use std::{error::Error, fmt, io, str::FromStr};
+
+/// Why recording a row or the whole run failed.
+#[derive(Debug)]
+pub enum RecorderError {
+    /// The Appium server couldn't be reached or the connection failed
+    Http(reqwest::Error),
+    /// The server replied with an error or with JSON of an unexpected shape
+    Protocol(String),
+    /// The session doesn't exist, has ended or couldn't be created
+    Session(String),
+    /// Reading stdin or a file, or writing the output, failed
+    Io(io::Error),
+    /// A line on stdin isn't a `FEATURE STAGE` marker
+    Input(String),
+}
+
+/// What to do when recording a row fails with a recoverable error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Report the error and carry on with the next row
+    Continue,
+    /// Stop recording, keeping the rows written so far
+    Abort,
+}
+
+impl RecorderError {
+    /// Whether recording can go on after this error. Without a session or an
+    /// output to write to, every later row would fail too.
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(self, RecorderError::Session(_) | RecorderError::Io(_))
+    }
+}
+
+impl fmt::Display for RecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecorderError::Http(err) => write!(f, "HTTP error: {err}"),
+            RecorderError::Protocol(message) => write!(f, "Protocol error: {message}"),
+            RecorderError::Session(message) => write!(f, "Session error: {message}"),
+            RecorderError::Io(err) => write!(f, "IO error: {err}"),
+            RecorderError::Input(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for RecorderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RecorderError::Http(err) => Some(err),
+            RecorderError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for RecorderError {
+    fn from(err: reqwest::Error) -> Self {
+        RecorderError::Http(err)
+    }
+}
+
+impl From<io::Error> for RecorderError {
+    fn from(err: io::Error) -> Self {
+        RecorderError::Io(err)
+    }
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "continue" => Ok(ErrorPolicy::Continue),
+            "abort" => Ok(ErrorPolicy::Abort),
+            _ => Err(format!("ON_ERROR must be 'continue' or 'abort', not '{s}'").into()),
+        }
+    }
+}
+
+impl ErrorPolicy {
+    /// Reports `err` and returns `Ok` if recording should carry on, otherwise
+    /// returns `err` so the caller stops.
+    pub fn handle(self, err: RecorderError) -> Result<(), RecorderError> {
+        if self == ErrorPolicy::Continue && err.is_recoverable() {
+            eprintln!("{err}");
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+}
@@ -1,21 +1,38 @@
-#![feature(fs_try_exists)]
-
 use dotenv::dotenv;
-use reqwest::blocking::Client;
-use serde_json::Value;
 use std::{
     collections::HashMap,
     env::args,
     error::Error,
-    fs::{self, File},
-    io::{self, BufRead, LineWriter, Read, Write},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead},
     path::Path,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
 
+use csv::{ReaderBuilder, Writer};
+
+use appium::Appium;
+use error::{ErrorPolicy, RecorderError};
+use sampler::Sampler;
+use schema::{DataType, Schema, UnknownFields, MISSING_VALUE};
+use session::{Capabilities, Selector};
+
+mod appium;
+mod error;
+mod sampler;
+mod schema;
+mod session;
+
+/// How long to wait for a session created from CAPABILITIES_FILE to be ready.
+const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(120);
+
 fn main() {
     dotenv().ok();
     if let Err(err) = run() {
         eprintln!("ERROR: {err}");
+        std::process::exit(1);
     }
 }
 
@@ -24,132 +41,494 @@ fn run() -> Result<(), Box<dyn Error>> {
         Some(val) => val,
         None => return Err("Argument for output file path required".into()),
     };
-    if let Ok(true) = fs::try_exists(&output_location) {
-        return Err("File already exists".into());
+    let data_types = match std::env::var("DATA_TYPES") {
+        Ok(val) => DataType::parse_list(&val)?,
+        Err(_) => vec![DataType::Memory],
+    };
+    let output_locations = output_locations(&output_location, &data_types);
+    for location in &output_locations {
+        if let Ok(true) = fs::exists(location) {
+            return Err(format!("File {location} already exists").into());
+        }
+    }
+
+    let appium = Appium::new(
+        reqwest::blocking::Client::new(),
+        std::env::var("CLIENT_URL")?,
+    );
+    let package_name = std::env::var("PACKAGE_NAME")?;
+    let error_policy = match std::env::var("ON_ERROR") {
+        Ok(val) => val.parse()?,
+        Err(_) => ErrorPolicy::Continue,
+    };
+    let session_id = match std::env::var("CAPABILITIES_FILE") {
+        Ok(capabilities_file) => {
+            if std::env::var("SESSION_ID").is_ok() || std::env::var("SESSION_CAPABILITIES").is_ok()
+            {
+                return Err(
+                    "CAPABILITIES_FILE can't be combined with SESSION_ID or SESSION_CAPABILITIES"
+                        .into(),
+                );
+            }
+            let timeout = match std::env::var("SESSION_TIMEOUT_MS") {
+                Ok(val) => Duration::from_millis(val.parse().map_err(|_| {
+                    "SESSION_TIMEOUT_MS must be a number of milliseconds".to_string()
+                })?),
+                Err(_) => DEFAULT_SESSION_TIMEOUT,
+            };
+            session::create_session(&appium, &capabilities_file, timeout)?
+        }
+        Err(_) => {
+            let capabilities = match std::env::var("SESSION_CAPABILITIES") {
+                Ok(val) => val.parse()?,
+                Err(_) => Capabilities::default(),
+            };
+            let selector = Selector::new(std::env::var("SESSION_ID").ok(), capabilities);
+            session::find_session(&appium, &selector)?
+        }
+    };
+
+    let unknown_fields = match std::env::var("UNKNOWN_FIELDS") {
+        Ok(val) => val.parse()?,
+        Err(_) => UnknownFields::Warn,
+    };
+    let sample_interval = match std::env::var("SAMPLE_INTERVAL_MS") {
+        Ok(val) => match val.parse() {
+            Ok(0) | Err(_) => {
+                return Err("SAMPLE_INTERVAL_MS must be a positive number of milliseconds".into())
+            }
+            Ok(millis) => Some(Duration::from_millis(millis)),
+        },
+        Err(_) => None,
+    };
+
+    let mut outputs = Vec::new();
+    for (&data_type, location) in data_types.iter().zip(output_locations) {
+        let mut schema = Schema::for_data_type(data_type, unknown_fields);
+        if sample_interval.is_some() {
+            schema = schema.with_events();
+        }
+        let mut output_file = Writer::from_path(&location)?;
+        output_file.write_record(schema.header())?;
+        output_file.flush()?;
+        outputs.push(Output {
+            location,
+            column_count: schema.column_count(),
+            schema,
+            output_file,
+        });
+    }
+
+    let fetch: Arc<Fetch<'static>> = Arc::new(move || {
+        data_types
+            .iter()
+            .map(|&data_type| appium.performance_data(&session_id, &package_name, data_type))
+            .collect()
+    });
+    let recorder = Arc::new(Mutex::new(Recorder {
+        error_policy,
+        outputs,
+        started: sample_interval.map(|_| Instant::now()),
+        feature: MISSING_VALUE.to_string(),
+        stage: MISSING_VALUE.to_string(),
+    }));
+    let (input, inputs) = mpsc::channel();
+    let sampler = sample_interval.map(|interval| {
+        let recorder = Arc::clone(&recorder);
+        let fetch = Arc::clone(&fetch);
+        let input = input.clone();
+        Sampler::spawn(interval, move || {
+            let tag = recorder.lock().unwrap().tag(Event::Sample);
+            if let Err(err) = record(&recorder, &*fetch, tag) {
+                let _ = input.send(Input::SampleFailed(err));
+            }
+        })
+    });
+    // stdin may not send another line for a long time, so it's read on its own
+    // thread and recording stops as soon as a sample fails
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if input.send(Input::Line(line)).is_err() {
+                return;
+            }
+        }
+        let _ = input.send(Input::End);
+    });
+
+    let result = loop {
+        let line = match inputs.recv() {
+            Ok(Input::Line(line)) => line,
+            Ok(Input::SampleFailed(err)) => break Err(err),
+            Ok(Input::End) | Err(_) => break Ok(()),
+        };
+        if let Err(err) = line
+            .map_err(RecorderError::from)
+            .and_then(|line| parse_input(&recorder, &*fetch, line))
+        {
+            break Err(err);
+        }
+    };
+    if let Some(sampler) = sampler {
+        sampler.stop();
+    }
+    // Keep what was recorded even if recording stopped early
+    recorder.lock().unwrap().finish()?;
+
+    Ok(result?)
+}
+
+/// The file each data type is recorded to: `output_location` itself for a
+/// single data type, otherwise one file per data type named after it, such as
+/// `metrics_cpuinfo.csv` for `metrics.csv`.
+fn output_locations(output_location: &str, data_types: &[DataType]) -> Vec<String> {
+    if let [_] = data_types {
+        return vec![output_location.to_owned()];
+    }
+
+    let path = Path::new(output_location);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    data_types
+        .iter()
+        .map(|data_type| {
+            let file_name = match path.extension() {
+                Some(extension) => format!(
+                    "{stem}_{}.{}",
+                    data_type.name(),
+                    extension.to_string_lossy()
+                ),
+                None => format!("{stem}_{}", data_type.name()),
+            };
+            path.with_file_name(file_name)
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect()
+}
+
+/// Replaces the header once appended fields have added columns, and pads the
+/// rows written before them.
+fn rewrite_header(output_location: &str, schema: &Schema) -> io::Result<()> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(output_location)?;
+    let mut rewritten = Writer::from_writer(Vec::new());
+    rewritten.write_record(schema.header())?;
+    for record in reader.records().skip(1) {
+        let record = record?;
+        let padding = schema.column_count().saturating_sub(record.len());
+        rewritten.write_record(
+            record
+                .iter()
+                .chain(std::iter::repeat_n(MISSING_VALUE, padding)),
+        )?;
     }
 
-    let client = reqwest::blocking::Client::new();
-    let mut buf = String::new();
+    let contents = rewritten.into_inner().map_err(|err| err.into_error())?;
+    fs::write(output_location, contents)
+}
+
+/// What caused a row to be recorded.
+#[derive(Debug, Clone, Copy)]
+enum Event {
+    /// A `FEATURE STAGE` line on stdin
+    Marker,
+    /// An interval sample between markers
+    Sample,
+}
 
-    let client_url = std::env::var("CLIENT_URL")?;
-    client
-        .get(format!("{client_url}/sessions"))
-        .send()?
-        .read_to_string(&mut buf)?;
-    let res: Value = serde_json::from_str(&buf)?;
-    let res = res.get("value").unwrap().as_array().unwrap();
-    if res.is_empty() {
-        return Err("Session not started".into());
+impl Event {
+    fn label(self) -> &'static str {
+        match self {
+            Event::Marker => "marker",
+            Event::Sample => "sample",
+        }
     }
+}
 
-    let res = res.get(0).unwrap().as_object().unwrap();
-    let session_id = res.get("id").unwrap().as_str().unwrap();
+/// What recording waits for on the main thread.
+enum Input {
+    /// A line read from stdin
+    Line(io::Result<String>),
+    /// stdin was closed
+    End,
+    /// A sample failed in a way that stops recording
+    SampleFailed(RecorderError),
+}
 
-    let output_file = File::create(Path::new(&output_location))?;
-    let mut output_file = LineWriter::new(output_file);
-    let handle = io::stdin().lock();
+/// Fetches the values of every data type, in the order of the outputs.
+type Fetch<'a> = dyn Fn() -> Vec<Result<HashMap<String, String>, RecorderError>> + Send + Sync + 'a;
 
-    output_file.write_all(b"feature,stage,dalvikPrivateDirty,dalvikPss,dalvikRss,eglPrivateDirty,eglPss,glPrivateDirty,glPss,nativeHeapAllocatedSize,nativeHeapSize,nativePrivateDirty,nativePss,nativeRss,totalPrivateDirty,totalPss,totalRss\n")?;
+/// Where one data type is recorded.
+struct Output {
+    location: String,
+    schema: Schema,
+    output_file: Writer<File>,
+    /// Columns in the header on disk. The header is rewritten as soon as
+    /// appended fields add columns, so the file is whole even if recording is
+    /// interrupted.
+    column_count: usize,
+}
 
-    for line in handle.lines() {
-        let line = line?;
-        if let Err(err) = parse_input(&client, session_id, line, &mut output_file) {
-            eprintln!("{err}");
+impl Output {
+    /// Writes a row, flushed so it's kept if recording is interrupted.
+    fn write_row(&mut self, tag: &[String], vals: Vec<String>) -> io::Result<()> {
+        if self.schema.column_count() != self.column_count {
+            self.output_file.flush()?;
+            rewrite_header(&self.location, &self.schema)?;
+            self.output_file =
+                Writer::from_writer(OpenOptions::new().append(true).open(&self.location)?);
+            self.column_count = self.schema.column_count();
         }
+
+        self.output_file.write_record(tag.iter().chain(&vals))?;
+        self.output_file.flush()
     }
-    output_file.flush()?;
+}
 
-    Ok(())
+/// Writes rows to the outputs, tagged with the feature and stage of the last
+/// marker. Shared between stdin and the sampling thread, which only lock it to
+/// tag and write rows, not while fetching their values.
+struct Recorder {
+    error_policy: ErrorPolicy,
+    outputs: Vec<Output>,
+    /// When recording began, if rows are tagged with their event
+    started: Option<Instant>,
+    feature: String,
+    stage: String,
 }
 
+impl Recorder {
+    /// The key columns of a row recorded now for `event`.
+    fn tag(&self, event: Event) -> Vec<String> {
+        let mut tag = vec![self.feature.clone(), self.stage.clone()];
+        if let Some(started) = self.started {
+            tag.push(event.label().to_string());
+            tag.push(started.elapsed().as_millis().to_string());
+        }
+        tag
+    }
+
+    /// Moves on to a marker's feature and stage and returns the tag of its row.
+    /// Samples after a `stop` belong to no feature until the next marker.
+    fn mark(&mut self, feature: &str, stage: &str) -> Vec<String> {
+        self.feature = feature.to_owned();
+        self.stage = stage.to_owned();
+        let tag = self.tag(Event::Marker);
+        if stage == "stop" {
+            self.feature = MISSING_VALUE.to_string();
+            self.stage = MISSING_VALUE.to_string();
+        }
+        tag
+    }
+
+    /// Writes a row for every data type fetched. A data type that failed is
+    /// handled by the error policy, so returns an error only when recording
+    /// should stop.
+    fn write(
+        &mut self,
+        tag: &[String],
+        fetched: Vec<Result<HashMap<String, String>, RecorderError>>,
+    ) -> Result<(), RecorderError> {
+        for (output, val_map) in self.outputs.iter_mut().zip(fetched) {
+            let val_map = match val_map {
+                Ok(val_map) => val_map,
+                Err(err) => {
+                    self.error_policy.handle(err)?;
+                    continue;
+                }
+            };
+
+            let vals = output.schema.row(&val_map);
+            output.write_row(tag, vals)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the outputs.
+    fn finish(&mut self) -> io::Result<()> {
+        for output in &mut self.outputs {
+            output.output_file.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fetches a row for every data type, without holding the lock, and writes it
+/// tagged with `tag`. Returns an error only when recording should stop.
+fn record(
+    recorder: &Mutex<Recorder>,
+    fetch: &Fetch<'_>,
+    tag: Vec<String>,
+) -> Result<(), RecorderError> {
+    let fetched = fetch();
+    recorder.lock().unwrap().write(&tag, fetched)
+}
+
+/// Records the marker in `line`. Returns an error only when recording should stop.
 fn parse_input(
-    client: &Client,
-    session_id: &str,
+    recorder: &Mutex<Recorder>,
+    fetch: &Fetch<'_>,
     line: String,
-    output_file: &mut LineWriter<File>,
-) -> Result<(), Box<dyn Error>> {
-    let mut buf = String::new();
+) -> Result<(), RecorderError> {
+    let tag = match parse_marker(&line) {
+        Ok((feature, stage)) => recorder.lock().unwrap().mark(feature, stage),
+        Err(err) => {
+            return recorder
+                .lock()
+                .unwrap()
+                .error_policy
+                .handle(RecorderError::Input(err.to_string()))
+        }
+    };
+
+    record(recorder, fetch, tag)
+}
+
+fn parse_marker(line: &str) -> Result<(&str, &str), &'static str> {
     let (feature, stage) = match line.split_once(' ') {
         Some((feature, stage)) => (feature, stage),
-        _ => return Err("Cannot split input into feature and stage".into()),
+        _ => return Err("Cannot split input into feature and stage"),
     };
     if stage
         .bytes()
         .fold(0, |acc, e| if e == b' ' { acc + 1 } else { acc })
         != 0
     {
-        return Err("Input must be of the format FEATURE STAGE".into());
+        return Err("Input must be of the format FEATURE STAGE");
     }
     if stage != "start" && stage != "stop" {
-        return Err("Stage must be either 'start' or 'stop'".into());
+        return Err("Stage must be either 'start' or 'stop'");
     }
 
-    let client_url = std::env::var("CLIENT_URL")?;
-    let package_name = std::env::var("PACKAGE_NAME")?;
-    client
-        .post(format!(
-            "{client_url}/session/{session_id}/appium/getPerformanceData"
-        ))
-        .body(format!(
-            "{{\"packageName\":\"{package_name}\",\"dataType\":\"memoryinfo\"}}"
-        ))
-        .send()?
-        .read_to_string(&mut buf)?;
-    let res: Value = serde_json::from_str(&buf)?;
-    let res = res.get("value").unwrap();
+    Ok((feature, stage))
+}
 
-    if let Some(res) = res.get("error") {
-        let err = res.get("error").unwrap().as_str().unwrap();
-        return Err(err.into());
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex, time::Instant};
+
+    use csv::{ReaderBuilder, Writer};
+
+    use super::{parse_input, record, rewrite_header, Event, Fetch, Output, Recorder};
+    use crate::{
+        error::{ErrorPolicy, RecorderError},
+        schema::{Schema, UnknownFields, MISSING_VALUE},
+    };
+
+    /// A recorder sampling one data type with a single field, `a`.
+    fn recorder(name: &str) -> (PathBuf, Mutex<Recorder>) {
+        let location = std::env::temp_dir().join(format!(
+            "apptester_record_metrics_{}_{name}.csv",
+            std::process::id()
+        ));
+        let schema = Schema::new(&["a"], UnknownFields::Warn).with_events();
+        let mut output_file = Writer::from_path(&location).unwrap();
+        output_file.write_record(schema.header()).unwrap();
+        let output = Output {
+            location: location.to_string_lossy().into_owned(),
+            column_count: schema.column_count(),
+            schema,
+            output_file,
+        };
+
+        let recorder = Recorder {
+            error_policy: ErrorPolicy::Abort,
+            outputs: vec![output],
+            started: Some(Instant::now()),
+            feature: MISSING_VALUE.to_string(),
+            stage: MISSING_VALUE.to_string(),
+        };
+        (location, Mutex::new(recorder))
     }
 
-    output_file.write_all(format!("{feature},{stage},").as_bytes())?;
+    fn fetch_a() -> Vec<Result<HashMap<String, String>, RecorderError>> {
+        vec![Ok(HashMap::from([("a".to_string(), "1".to_string())]))]
+    }
 
-    if let Some(arrays) = res.as_array() {
-        let vals = vals_from_arrays(arrays);
-        write_vals_to_file(output_file, vals)?;
+    /// The feature, stage and event of each row.
+    fn tags(location: &PathBuf) -> Vec<String> {
+        ReaderBuilder::new()
+            .from_path(location)
+            .unwrap()
+            .records()
+            .map(|record| record.unwrap().iter().take(3).collect::<Vec<_>>().join(" "))
+            .collect()
     }
 
-    output_file.write_all(b"\n")?;
-    Ok(())
-}
+    fn sample(recorder: &Mutex<Recorder>, fetch: &Fetch<'_>) {
+        let tag = recorder.lock().unwrap().tag(Event::Sample);
+        record(recorder, fetch, tag).unwrap();
+    }
 
-fn write_vals_to_file(
-    output_file: &mut LineWriter<File>,
-    vals: Vec<String>,
-) -> Result<(), Box<dyn Error>> {
-    output_file.write_all(vals.join(",").as_bytes())?;
-    Ok(())
-}
+    #[test]
+    fn samples_are_tagged_with_the_last_marker_until_it_stops() {
+        let (location, recorder) = recorder("tags");
 
-fn vals_from_sorted_val_vec(val_vec: Vec<(String, String)>) -> Vec<String> {
-    val_vec
-        .iter()
-        .map(|(_, s)| s.to_owned())
-        .collect::<Vec<String>>()
-}
+        sample(&recorder, &fetch_a);
+        parse_input(&recorder, &fetch_a, String::from("Files start")).unwrap();
+        sample(&recorder, &fetch_a);
+        parse_input(&recorder, &fetch_a, String::from("Files stop")).unwrap();
+        sample(&recorder, &fetch_a);
 
-fn sorted_val_vec_from_val_map(val_map: HashMap<String, String>) -> Vec<(String, String)> {
-    let mut val_vec: Vec<(String, String)> = val_map.into_iter().collect();
-    val_vec.sort_by_key(|k| k.clone().0);
-    val_vec
-}
+        assert_eq!(
+            tags(&location),
+            vec![
+                "NA NA sample",
+                "Files start marker",
+                "Files start sample",
+                "Files stop marker",
+                "NA NA sample",
+            ]
+        );
+        fs::remove_file(location).unwrap();
+    }
 
-fn vals_from_arrays(arrays: &[Value]) -> Vec<String> {
-    let (&arr0, &arr1) = (
-        &arrays[0].as_array().unwrap(),
-        &arrays[1].as_array().unwrap(),
-    );
-    let mut val_map: HashMap<String, String> = HashMap::new();
+    #[test]
+    fn markers_are_recorded_while_a_sample_is_fetched() {
+        let (location, recorder) = recorder("interleaved");
+        parse_input(&recorder, &fetch_a, String::from("Files start")).unwrap();
+
+        // A marker arriving mid-fetch would deadlock if fetching held the lock
+        let slow_fetch = || {
+            parse_input(&recorder, &fetch_a, String::from("Files stop")).unwrap();
+            fetch_a()
+        };
+        sample(&recorder, &slow_fetch);
 
-    for (val0, val1) in arr0.iter().zip(arr1.iter()) {
-        val_map.insert(
-            val0.as_str().unwrap().to_string(),
-            val1.as_str().unwrap_or("").to_string(),
+        assert_eq!(
+            tags(&location),
+            vec![
+                "Files start marker",
+                "Files stop marker",
+                "Files start sample"
+            ]
         );
+        fs::remove_file(location).unwrap();
     }
 
-    let val_vec = sorted_val_vec_from_val_map(val_map);
+    #[test]
+    fn rewrite_header_pads_rows_keeping_quoted_values() {
+        let location = std::env::temp_dir().join(format!(
+            "apptester_record_metrics_{}_header.csv",
+            std::process::id()
+        ));
+        fs::write(
+            &location,
+            "feature,stage,a\n\"Files, Photos\",start,1\nFiles,stop,\"say \"\"hi\"\"\nthere\"\n",
+        )
+        .unwrap();
+        let mut schema = Schema::new(&["a"], UnknownFields::Append);
+        let fields = HashMap::from([("b,c".to_string(), "2".to_string())]);
+        schema.row(&fields);
 
-    vals_from_sorted_val_vec(val_vec)
+        rewrite_header(location.to_str().unwrap(), &schema).unwrap();
+        assert_eq!(
+            fs::read_to_string(&location).unwrap(),
+            "feature,stage,a,\"b,c\"\n\"Files, Photos\",start,1,NA\nFiles,stop,\"say \"\"hi\"\"\nthere\",NA\n"
+        );
+        fs::remove_file(location).unwrap();
+    }
 }
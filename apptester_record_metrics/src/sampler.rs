@@ -0,0 +1,68 @@
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Calls a function every interval on a background thread until stopped.
+pub struct Sampler {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl Sampler {
+    /// Starts calling `sample` every `interval`, the first time one interval
+    /// from now. A slow sample delays the next one rather than queueing more.
+    pub fn spawn(interval: Duration, mut sample: impl FnMut() + Send + 'static) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut next = Instant::now() + interval;
+            loop {
+                match stopped.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                    Err(RecvTimeoutError::Timeout) => sample(),
+                    _ => return,
+                }
+                next = (next + interval).max(Instant::now());
+            }
+        });
+
+        Self { stop, handle }
+    }
+
+    /// Stops sampling, waiting for a sample in progress to finish.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use super::Sampler;
+
+    #[test]
+    fn sampler_samples_every_interval_until_stopped() {
+        let samples = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&samples);
+        let sampler = Sampler::spawn(Duration::from_millis(20), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        thread::sleep(Duration::from_millis(110));
+        sampler.stop();
+        let count = samples.load(Ordering::SeqCst);
+        assert!((3..=6).contains(&count), "{count} samples");
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(samples.load(Ordering::SeqCst), count);
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    str::FromStr,
+};
+
+/// Written in place of a field the device didn't return.
+pub const MISSING_VALUE: &str = "NA";
+
+/// Columns written before every data type's own columns.
+const KEY_COLUMNS: [&str; 2] = ["feature", "stage"];
+
+/// Key columns added when samples are taken between markers.
+const EVENT_COLUMNS: [&str; 2] = ["event", "elapsed_ms"];
+
+const MEMORY_INFO_COLUMNS: [&str; 15] = [
+    "dalvikPrivateDirty",
+    "dalvikPss",
+    "dalvikRss",
+    "eglPrivateDirty",
+    "eglPss",
+    "glPrivateDirty",
+    "glPss",
+    "nativeHeapAllocatedSize",
+    "nativeHeapSize",
+    "nativePrivateDirty",
+    "nativePss",
+    "nativeRss",
+    "totalPrivateDirty",
+    "totalPss",
+    "totalRss",
+];
+
+const CPU_INFO_COLUMNS: [&str; 2] = ["user", "kernel"];
+
+const BATTERY_INFO_COLUMNS: [&str; 1] = ["power"];
+
+const NETWORK_INFO_COLUMNS: [&str; 8] = [
+    "bucketStart",
+    "activeTime",
+    "rxBytes",
+    "rxPackets",
+    "txBytes",
+    "txPackets",
+    "operations",
+    "bucketDuration",
+];
+
+/// A kind of performance data Appium's `getPerformanceData` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Memory,
+    Cpu,
+    Battery,
+    Network,
+}
+
+/// What to do with fields the schema doesn't declare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Leave them out and warn once per field
+    Warn,
+    /// Add a column for them after the declared ones
+    Append,
+}
+
+/// The columns of the output, so each value lands under its field's name.
+#[derive(Debug)]
+pub struct Schema {
+    key_columns: Vec<&'static str>,
+    columns: Vec<String>,
+    unknown_fields: UnknownFields,
+    warned: HashSet<String>,
+}
+
+impl FromStr for UnknownFields {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(UnknownFields::Warn),
+            "append" => Ok(UnknownFields::Append),
+            _ => Err(format!("UNKNOWN_FIELDS must be 'warn' or 'append', not '{s}'").into()),
+        }
+    }
+}
+
+impl DataType {
+    /// The `dataType` Appium knows it by.
+    pub fn name(self) -> &'static str {
+        match self {
+            DataType::Memory => "memoryinfo",
+            DataType::Cpu => "cpuinfo",
+            DataType::Battery => "batteryinfo",
+            DataType::Network => "networkinfo",
+        }
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            DataType::Memory => &MEMORY_INFO_COLUMNS,
+            DataType::Cpu => &CPU_INFO_COLUMNS,
+            DataType::Battery => &BATTERY_INFO_COLUMNS,
+            DataType::Network => &NETWORK_INFO_COLUMNS,
+        }
+    }
+
+    /// Parses a comma-separated list such as `memoryinfo,cpuinfo`.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, Box<dyn Error>> {
+        let mut data_types = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let data_type = name.parse()?;
+            if !data_types.contains(&data_type) {
+                data_types.push(data_type);
+            }
+        }
+        if data_types.is_empty() {
+            return Err("DATA_TYPES must name at least one data type".into());
+        }
+
+        Ok(data_types)
+    }
+}
+
+impl FromStr for DataType {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memoryinfo" => Ok(DataType::Memory),
+            "cpuinfo" => Ok(DataType::Cpu),
+            "batteryinfo" => Ok(DataType::Battery),
+            "networkinfo" => Ok(DataType::Network),
+            _ => Err(format!(
+                "Unknown data type '{s}', expected memoryinfo, cpuinfo, batteryinfo or networkinfo"
+            )
+            .into()),
+        }
+    }
+}
+
+impl Schema {
+    pub fn new(columns: &[&str], unknown_fields: UnknownFields) -> Self {
+        Self {
+            key_columns: KEY_COLUMNS.to_vec(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            unknown_fields,
+            warned: HashSet::new(),
+        }
+    }
+
+    pub fn for_data_type(data_type: DataType, unknown_fields: UnknownFields) -> Self {
+        Self::new(data_type.columns(), unknown_fields)
+    }
+
+    /// Adds the columns that tell markers and interval samples apart.
+    pub fn with_events(mut self) -> Self {
+        self.key_columns.extend(EVENT_COLUMNS);
+        self
+    }
+
+    pub fn header(&self) -> Vec<&str> {
+        self.key_columns
+            .iter()
+            .copied()
+            .chain(self.columns.iter().map(String::as_str))
+            .collect()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.key_columns.len() + self.columns.len()
+    }
+
+    /// The values of `fields` in column order, with [`MISSING_VALUE`] for
+    /// columns the fields don't have. Unknown fields are appended as new
+    /// columns or left out, in sorted order so rows are stable.
+    pub fn row(&mut self, fields: &HashMap<String, String>) -> Vec<String> {
+        let mut unknown: Vec<&String> = fields
+            .keys()
+            .filter(|name| !self.columns.contains(name))
+            .collect();
+        unknown.sort();
+        for name in unknown {
+            match self.unknown_fields {
+                UnknownFields::Append => self.columns.push(name.to_owned()),
+                UnknownFields::Warn => {
+                    if self.warned.insert(name.to_owned()) {
+                        eprintln!("WARNING: ignoring unknown field '{name}'");
+                    }
+                }
+            }
+        }
+
+        self.columns
+            .iter()
+            .map(|column| match fields.get(column) {
+                Some(value) => value.to_owned(),
+                None => MISSING_VALUE.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{DataType, Schema, UnknownFields};
+
+    fn fields(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn row_maps_fields_to_columns_by_name() {
+        let mut schema = Schema::new(&["a", "b", "c"], UnknownFields::Warn);
+        let row = schema.row(&fields(&[("c", "3"), ("a", "1"), ("z", "26")]));
+
+        assert_eq!(schema.header().join(","), "feature,stage,a,b,c");
+        assert_eq!(row, vec!["1", "NA", "3"]);
+        assert_eq!(
+            schema.with_events().header().join(","),
+            "feature,stage,event,elapsed_ms,a,b,c"
+        );
+    }
+
+    #[test]
+    fn row_appends_unknown_fields_when_asked() {
+        let mut schema = Schema::new(&["a"], UnknownFields::Append);
+        let row = schema.row(&fields(&[("z", "26"), ("a", "1"), ("y", "")]));
+
+        assert_eq!(schema.header().join(","), "feature,stage,a,y,z");
+        assert_eq!(row, vec!["1", "", "26"]);
+        assert_eq!(schema.row(&fields(&[("a", "2")])), vec!["2", "NA", "NA"]);
+    }
+
+    #[test]
+    fn data_types_parse_from_a_list_of_appium_names() {
+        assert_eq!(
+            DataType::parse_list("cpuinfo, memoryinfo,cpuinfo").unwrap(),
+            vec![DataType::Cpu, DataType::Memory]
+        );
+        assert!(DataType::parse_list("cpuinfo,diskinfo").is_err());
+        assert!(DataType::parse_list(" , ").is_err());
+        assert_eq!(
+            Schema::for_data_type(DataType::Battery, UnknownFields::Warn)
+                .header()
+                .join(","),
+            "feature,stage,power"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    error::Error,
+    fs,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    appium::{Appium, Session},
+    error::RecorderError,
+};
+
+/// How often to check whether a created session is ready.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Which of the server's sessions to record.
+#[derive(Debug, Default)]
+pub struct Selector {
+    id: Option<String>,
+    capabilities: Vec<(String, String)>,
+}
+
+/// Capabilities a session must have, e.g. `deviceName=Pixel 7,udid=emulator-5554`.
+#[derive(Debug, Default)]
+pub struct Capabilities(Vec<(String, String)>);
+
+impl FromStr for Capabilities {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) => Ok((name.trim().to_owned(), value.trim().to_owned())),
+                None => Err(format!(
+                    "SESSION_CAPABILITIES must be of the format NAME=VALUE,..., not '{pair}'"
+                )
+                .into()),
+            })
+            .collect::<Result<_, _>>()
+            .map(Capabilities)
+    }
+}
+
+impl Selector {
+    pub fn new(id: Option<String>, capabilities: Capabilities) -> Self {
+        Self {
+            id,
+            capabilities: capabilities.0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.id.is_none() && self.capabilities.is_empty()
+    }
+
+    /// Whether a session from `GET /sessions` is the one selected. Capabilities
+    /// match with or without the `appium:` vendor prefix.
+    fn matches(&self, session: &Session) -> bool {
+        if self.id.as_ref().is_some_and(|id| *id != session.id) {
+            return false;
+        }
+
+        self.capabilities.iter().all(|(name, expected)| {
+            let actual = session
+                .capabilities
+                .get(name)
+                .or_else(|| session.capabilities.get(&format!("appium:{name}")));
+            match actual {
+                Some(Value::String(actual)) => actual == expected,
+                Some(actual) => serde_json::from_str::<Value>(expected)
+                    .is_ok_and(|expected| expected == *actual),
+                None => false,
+            }
+        })
+    }
+}
+
+/// The id of the selected session among those the server has running. Without
+/// a selector, the first session is used.
+pub fn find_session(appium: &Appium, selector: &Selector) -> Result<String, RecorderError> {
+    let sessions = appium.sessions()?;
+    if sessions.is_empty() {
+        return Err(RecorderError::Session("Session not started".to_string()));
+    }
+
+    let matching: Vec<&str> = sessions
+        .iter()
+        .filter(|session| selector.matches(session))
+        .map(|session| session.id.as_str())
+        .collect();
+    match matching[..] {
+        [] => Err(RecorderError::Session(
+            "No session matches SESSION_ID and SESSION_CAPABILITIES".to_string(),
+        )),
+        [id] => Ok(id.to_owned()),
+        [id, ..] if selector.is_empty() => {
+            eprintln!(
+                "WARNING: {} sessions running, recording {id}. Set SESSION_ID or SESSION_CAPABILITIES to choose another",
+                matching.len()
+            );
+            Ok(id.to_owned())
+        }
+        _ => Err(RecorderError::Session(format!(
+            "Several sessions match SESSION_ID and SESSION_CAPABILITIES: {}",
+            matching.join(", ")
+        ))),
+    }
+}
+
+/// Creates a session from the capabilities in `capabilities_file` and waits up
+/// to `timeout` until the server reports it ready, returning its id. The file
+/// holds either a full `{"capabilities": ...}` request or just the
+/// capabilities. The session is left running once recording ends.
+pub fn create_session(
+    appium: &Appium,
+    capabilities_file: &str,
+    timeout: Duration,
+) -> Result<String, RecorderError> {
+    let capabilities: Value = serde_json::from_str(&fs::read_to_string(capabilities_file)?)
+        .map_err(|err| RecorderError::Session(format!("Invalid {capabilities_file}: {err}")))?;
+    let body = match capabilities.get("capabilities") {
+        Some(_) => capabilities,
+        None => json!({ "capabilities": { "alwaysMatch": capabilities } }),
+    };
+
+    // Creating the session and waiting for it share the one timeout
+    let deadline = Instant::now() + timeout;
+    let session_id = appium.create_session(&body, timeout)?;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(RecorderError::Session(format!(
+                "Session {session_id} wasn't ready within {timeout:?}"
+            )));
+        }
+        if appium.session_ready(&session_id, remaining) {
+            eprintln!("Created session {session_id}");
+            return Ok(session_id);
+        }
+        thread::sleep(POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Capabilities, Selector};
+    use crate::appium::Session;
+
+    #[test]
+    fn selector_matches_sessions_by_id_and_capabilities() {
+        let session: Session = serde_json::from_value(json!({
+            "id": "a1",
+            "capabilities": {
+                "deviceName": "Pixel 7",
+                "appium:udid": "emulator-5554",
+                "newCommandTimeout": 60
+            }
+        }))
+        .unwrap();
+        let selector = |id: Option<&str>, capabilities: &str| {
+            Selector::new(id.map(str::to_owned), capabilities.parse().unwrap())
+        };
+
+        assert!(selector(None, "").matches(&session));
+        assert!(selector(Some("a1"), "").matches(&session));
+        assert!(!selector(Some("b2"), "").matches(&session));
+        assert!(selector(None, "deviceName=Pixel 7, udid=emulator-5554").matches(&session));
+        assert!(selector(None, "newCommandTimeout=60").matches(&session));
+        assert!(!selector(Some("a1"), "appPackage=com.example").matches(&session));
+        assert!("deviceName".parse::<Capabilities>().is_err());
+    }
+}
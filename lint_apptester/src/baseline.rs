@@ -0,0 +1,227 @@
+//! A record of existing diagnostics, so CI only fails on new ones.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cache::hash, changes::ChangeSet, Diagnostic, Report, Result};
+
+pub const BASELINE_FILE_NAME: &str = "lint_apptester_baseline.json";
+
+/// Diagnostics to ignore, written by `--write-baseline`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    entries: Vec<BaselineEntry>,
+}
+
+/// Diagnostics of one rule on identical lines of one file. Line numbers are
+/// left out so entries still match after code above them moves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    fingerprint: String,
+    rule_id: String,
+    /// Relative to the project root
+    path: String,
+    text: String,
+    count: usize,
+}
+
+impl Baseline {
+    pub fn default_path(project_root: &str) -> PathBuf {
+        Path::new(project_root).join(BASELINE_FILE_NAME)
+    }
+
+    pub fn from_diagnostics(project_root: &str, diagnostics: &[Diagnostic]) -> Self {
+        let mut entries: Vec<BaselineEntry> = Vec::new();
+        for diagnostic in diagnostics {
+            let entry = BaselineEntry::new(project_root, diagnostic);
+            match entries
+                .iter_mut()
+                .find(|existing| existing.fingerprint == entry.fingerprint)
+            {
+                Some(existing) => existing.count += 1,
+                None => entries.push(entry),
+            }
+        }
+        entries.sort_by(|a, b| (&a.path, &a.rule_id, &a.text).cmp(&(&b.path, &b.rule_id, &b.text)));
+
+        Self { entries }
+    }
+
+    /// Reads the baseline at `path`, or `None` if there isn't one.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+        match serde_json::from_str(&contents) {
+            Ok(baseline) => Ok(Some(baseline)),
+            Err(err) => Err(format!("could not parse {}: {err}", path.display()).into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    pub fn get_entries(&self) -> &Vec<BaselineEntry> {
+        &self.entries
+    }
+
+    /// Removes baselined diagnostics from `report` and returns the entries that
+    /// no longer match anything, i.e. that have been fixed. Entries for files
+    /// that weren't linted, because they're outside the report's subdirs or
+    /// unchanged, are never counted as fixed.
+    pub fn apply(&self, report: &mut Report, changes: Option<&ChangeSet>) -> Vec<&BaselineEntry> {
+        let project_root = report.get_project_root().to_owned();
+        let mut remaining: HashMap<&str, usize> = self
+            .entries
+            .iter()
+            .map(|entry| (entry.fingerprint.as_str(), entry.count))
+            .collect();
+
+        report.retain_diagnostics(|diagnostic| {
+            let fingerprint = fingerprint(&project_root, diagnostic);
+            match remaining.get_mut(fingerprint.as_str()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            }
+        });
+
+        let linted_paths: Vec<&Path> = report
+            .get_features()
+            .iter()
+            .flat_map(|feature| feature.get_subdirs())
+            .map(|subdir| subdir.get_path())
+            .collect();
+        self.entries
+            .iter()
+            .filter(|entry| remaining[entry.fingerprint.as_str()] > 0)
+            .filter(|entry| {
+                let path = Path::new(&project_root).join(&entry.path);
+                linted_paths
+                    .iter()
+                    .any(|linted_path| path.starts_with(linted_path))
+                    && changes.is_none_or(|changes| changes.includes_file(&path))
+            })
+            .collect()
+    }
+}
+
+impl BaselineEntry {
+    fn new(project_root: &str, diagnostic: &Diagnostic) -> Self {
+        Self {
+            fingerprint: fingerprint(project_root, diagnostic),
+            rule_id: String::from(diagnostic.get_rule_id()),
+            path: relative_path(project_root, diagnostic.get_path()),
+            text: normalise_text(diagnostic.get_text()),
+            count: 1,
+        }
+    }
+
+    pub fn get_rule_id(&self) -> &str {
+        &self.rule_id
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A hash of the diagnostic's rule, file and line content, ignoring whitespace
+/// changes.
+fn fingerprint(project_root: &str, diagnostic: &Diagnostic) -> String {
+    let key = format!(
+        "{}\0{}\0{}",
+        diagnostic.get_rule_id(),
+        relative_path(project_root, diagnostic.get_path()),
+        normalise_text(diagnostic.get_text())
+    );
+
+    hash(key.as_bytes())
+}
+
+fn relative_path(project_root: &str, path: &Path) -> String {
+    path.strip_prefix(project_root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn normalise_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::Baseline;
+    use crate::{Diagnostic, FeatureReport, Report, RuleReport, Severity, SubdirReport};
+
+    fn report_with(diagnostics: Vec<Diagnostic>) -> Report {
+        let mut feature_report = FeatureReport::new("files");
+        feature_report.add_subdir(SubdirReport {
+            dir_type: crate::DirType::Steps,
+            path: PathBuf::from("/repo/steps/files"),
+            rules: vec![RuleReport {
+                id: String::from("rule"),
+                description: String::new(),
+                severity: Severity::Error,
+                diagnostics,
+            }],
+        });
+        let mut report = Report::new("/repo/", false);
+        report.add_feature(feature_report);
+        report
+    }
+
+    fn diagnostic(path: &str, line: usize, text: &str) -> Diagnostic {
+        Diagnostic::new("rule", Path::new(path), line, 1, text, "")
+    }
+
+    #[test]
+    fn baseline_suppresses_known_diagnostics_after_lines_move() {
+        let baseline = Baseline::from_diagnostics(
+            "/repo/",
+            &[
+                diagnostic("/repo/steps/files/A.java", 3, "a();"),
+                diagnostic("/repo/steps/files/A.java", 4, "a();"),
+                diagnostic("/repo/steps/files/A.java", 5, "b();"),
+                diagnostic("/repo/steps/photos/B.java", 5, "c();"),
+            ],
+        );
+        assert_eq!(baseline.get_entries().len(), 3);
+        assert_eq!(baseline.get_entries()[0].get_path(), "steps/files/A.java");
+
+        let mut report = report_with(vec![
+            diagnostic("/repo/steps/files/A.java", 10, "a();"),
+            diagnostic("/repo/steps/files/A.java", 11, "a( );"),
+            diagnostic("/repo/steps/files/A.java", 12, "a(); "),
+            diagnostic("/repo/steps/files/A.java", 13, "a();"),
+        ]);
+        let fixed = baseline.apply(&mut report, None);
+
+        let lines: Vec<usize> = report
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.get_line())
+            .collect();
+        assert_eq!(lines, vec![11, 13]);
+        // B.java is in a subdir that wasn't linted
+        assert_eq!(fixed.len(), 1);
+        assert_eq!(fixed[0].get_text(), "b();");
+    }
+}
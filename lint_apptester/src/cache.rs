@@ -0,0 +1,201 @@
+//! An on-disk cache of each file's diagnostics, so unchanged files aren't
+//! linted again.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Diagnostic, DirType, Result, Rules};
+
+pub const CACHE_DIR_NAME: &str = ".lint_apptester_cache";
+const CACHE_FILE_NAME: &str = "results.json";
+
+/// Environment variables that change what rules report, on top of the rule
+/// configuration in `lint_apptester.toml`.
+const RULE_ENV_VARS: [&str; 5] = [
+    "REPOSITORY_PATH",
+    "LOCATOR_CLASS_PATH",
+    "LOGGER_CALL",
+    "LOGGER_FIELD",
+    "FOLLOW_SYMLINKS",
+];
+
+/// Cached diagnostics, valid for one tool version and rule configuration.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    version: String,
+    rules_hash: String,
+    files: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+/// Diagnostics of one file before suppressions are applied, by rule id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    content_hash: String,
+    diagnostics: HashMap<String, Vec<Diagnostic>>,
+}
+
+impl Cache {
+    pub fn dir(project_root: &str) -> PathBuf {
+        Path::new(project_root).join(CACHE_DIR_NAME)
+    }
+
+    /// An empty cache that's only kept in memory unless saved.
+    pub fn new(rules: &Rules) -> Self {
+        Self {
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            rules_hash: rules_hash(rules),
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reads the project's cache, starting again with an empty one if it was
+    /// written by another version or with different rules.
+    pub fn load(project_root: &str, rules: &Rules) -> Result<Self> {
+        let empty = Self::new(rules);
+
+        let path = Self::dir(project_root).join(CACHE_FILE_NAME);
+        let cache = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Self>(&contents).ok());
+        match cache {
+            Some(cache)
+                if cache.version == empty.version && cache.rules_hash == empty.rules_hash =>
+            {
+                Ok(cache)
+            }
+            Some(_) => {
+                fs::remove_dir_all(Self::dir(project_root))?;
+                Ok(empty)
+            }
+            None => Ok(empty),
+        }
+    }
+
+    /// Writes the cache, leaving out files that no longer exist.
+    pub fn save(&self, project_root: &str) -> Result<()> {
+        self.files.lock().unwrap().retain(|path, _| path.exists());
+
+        let dir = Self::dir(project_root);
+        fs::create_dir_all(&dir)?;
+        // Keep the cache out of git without touching the project's .gitignore
+        fs::write(dir.join(".gitignore"), "*\n")?;
+        fs::write(dir.join(CACHE_FILE_NAME), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The cached diagnostics of `path`, if its contents haven't changed.
+    pub fn get(&self, path: &Path, contents: &str) -> Option<HashMap<String, Vec<Diagnostic>>> {
+        let files = self.files.lock().unwrap();
+        let entry = files.get(path)?;
+        (entry.content_hash == content_hash(contents.as_bytes())).then(|| entry.diagnostics.clone())
+    }
+
+    pub fn insert(
+        &self,
+        path: &Path,
+        contents: &str,
+        diagnostics: HashMap<String, Vec<Diagnostic>>,
+    ) {
+        self.files.lock().unwrap().insert(
+            path.to_path_buf(),
+            CacheEntry {
+                content_hash: content_hash(contents.as_bytes()),
+                diagnostics,
+            },
+        );
+    }
+}
+
+/// A hash of everything that decides which diagnostics rules report.
+fn rules_hash(rules: &Rules) -> String {
+    let mut key = String::new();
+    for rule in rules.get_rules() {
+        let rule = rule.as_ref();
+        let dir_types: Vec<&str> = rules
+            .get_dir_types(rule)
+            .iter()
+            .map(DirType::label)
+            .collect();
+        key.push_str(&format!(
+            "{} {} {} {:?} {:?}\n",
+            rule.id(),
+            rules.is_enabled(rule),
+            rules.get_severity(rule).label(),
+            dir_types,
+            rules.get_rule_config(rule)
+        ));
+    }
+    key.push_str(&format!("{:?}\n", rules.get_custom_rules()));
+    for env_var in RULE_ENV_VARS
+        .iter()
+        .chain(&DirType::ALL.map(|dir_type| dir_type.path_var()))
+    {
+        key.push_str(&format!("{env_var}={:?}\n", std::env::var(env_var).ok()));
+    }
+
+    content_hash(key.as_bytes())
+}
+
+/// SHA-256, so a changed file can't be mistaken for the one cached.
+fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// FNV-1a, which unlike `DefaultHasher` gives the same hash in every build.
+pub fn hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, path::Path};
+
+    use super::Cache;
+    use crate::{rules::get_rules, Diagnostic, Rules};
+
+    #[test]
+    fn cache_replays_unchanged_files_and_clears_when_rules_change() {
+        let project_root = std::env::temp_dir().join("lint_apptester_cache_test");
+        let _ = fs::remove_dir_all(&project_root);
+        fs::create_dir_all(&project_root).unwrap();
+        let project_root = project_root.to_str().unwrap();
+        let path = Path::new(project_root).join("A.java");
+        fs::write(&path, "class A {}").unwrap();
+
+        let diagnostics = HashMap::from([(
+            String::from("rule"),
+            vec![Diagnostic::new(
+                "rule",
+                &path,
+                1,
+                1,
+                "class A {}",
+                "message",
+            )],
+        )]);
+        let cache = Cache::load(project_root, &get_rules()).unwrap();
+        cache.insert(&path, "class A {}", diagnostics.clone());
+        cache.save(project_root).unwrap();
+
+        let cache = Cache::load(project_root, &get_rules()).unwrap();
+        assert_eq!(cache.get(&path, "class A {}"), Some(diagnostics));
+        assert_eq!(cache.get(&path, "class A { }"), None);
+
+        let cache = Cache::load(project_root, &Rules::init()).unwrap();
+        assert_eq!(cache.get(&path, "class A {}"), None);
+        assert!(!Cache::dir(project_root).exists());
+    }
+}
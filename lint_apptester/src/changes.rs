@@ -0,0 +1,278 @@
+//! Files and lines changed in git, for `--changed-since` and `--staged`.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str,
+};
+
+use crate::{Diagnostic, Result};
+
+/// What to compare the project's files against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// Changes since the merge base of the ref and `HEAD`, including uncommitted
+    /// and untracked files
+    Since(String),
+    /// Changes staged for the next commit, linting the staged contents rather
+    /// than the files
+    Staged,
+}
+
+/// The changed files and, for each, the lines added or modified in it.
+#[derive(Debug, Default)]
+pub struct ChangeSet {
+    files: HashMap<PathBuf, BTreeSet<usize>>,
+    /// The staged contents of each changed file, with `--staged`
+    staged: HashMap<PathBuf, String>,
+    /// Whether only diagnostics on changed lines are reported
+    lines_only: bool,
+}
+
+impl ChangeSet {
+    pub fn load(project_root: &str, source: &ChangeSource, lines_only: bool) -> Result<Self> {
+        let toplevel = PathBuf::from(git(project_root, &["rev-parse", "--show-toplevel"])?.trim());
+
+        // Prefixes are set, as diff.noprefix or diff.mnemonicPrefix would change them
+        let diff_args = [
+            "diff",
+            "--unified=0",
+            "--no-color",
+            "--no-ext-diff",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+        ];
+        let diff = match source {
+            ChangeSource::Since(base_ref) => {
+                let merge_base = git(project_root, &["merge-base", base_ref, "HEAD"])?;
+                git(
+                    project_root,
+                    &[&diff_args[..], &[merge_base.trim()]].concat(),
+                )?
+            }
+            ChangeSource::Staged => git(project_root, &[&diff_args[..], &["--cached"]].concat())?,
+        };
+
+        let mut change_set = Self::parse_diff(&toplevel, &diff);
+        change_set.lines_only = lines_only;
+
+        // Staged changes are linted as staged, whatever the files now hold
+        if let ChangeSource::Staged = source {
+            let normalised_toplevel = normalise(&toplevel);
+            for path in change_set.files.keys() {
+                let relative = path.strip_prefix(&normalised_toplevel).unwrap_or(path);
+                let contents = git(project_root, &["show", &format!(":{}", relative.display())])?;
+                change_set.staged.insert(path.clone(), contents);
+            }
+        }
+
+        // New files aren't in the diff until they're added
+        if let ChangeSource::Since(_) = source {
+            let untracked = git(
+                project_root,
+                &["ls-files", "--others", "--exclude-standard", "--full-name"],
+            )?;
+            for path in untracked.lines() {
+                let path = toplevel.join(path);
+                let line_count = fs::read_to_string(&path)
+                    .map(|contents| contents.lines().count())
+                    .unwrap_or(0);
+                change_set
+                    .files
+                    .insert(normalise(&path), (1..=line_count).collect());
+            }
+        }
+
+        Ok(change_set)
+    }
+
+    /// Reads the output of `git diff --unified=0`, whose paths are relative to
+    /// `toplevel`.
+    pub fn parse_diff(toplevel: &Path, diff: &str) -> Self {
+        let mut files: HashMap<PathBuf, BTreeSet<usize>> = HashMap::new();
+        let mut current: Option<PathBuf> = None;
+        // Added lines can start with `+++` too, so only read paths from headers
+        let mut in_header = false;
+        for line in diff.lines() {
+            if line.starts_with("diff ") {
+                in_header = true;
+                continue;
+            }
+            if let Some(path) = line.strip_prefix("+++ ").filter(|_| in_header) {
+                // Deleted files have nothing left to lint
+                current = path
+                    .strip_prefix("b/")
+                    .map(|path| normalise(&toplevel.join(path)));
+                if let Some(path) = &current {
+                    files.entry(path.clone()).or_default();
+                }
+                continue;
+            }
+
+            // e.g. `@@ -10,2 +12,3 @@`, where the new lines are 12 to 14
+            let (Some(path), Some(hunk)) = (&current, line.strip_prefix("@@ ")) else {
+                continue;
+            };
+            in_header = false;
+            let Some(added) = hunk.split(' ').find_map(|range| range.strip_prefix('+')) else {
+                continue;
+            };
+            let (start, count) = match added.split_once(',') {
+                Some((start, count)) => (start.parse::<usize>(), count.parse::<usize>()),
+                None => (added.parse::<usize>(), Ok(1)),
+            };
+            if let (Ok(start), Ok(count)) = (start, count) {
+                files
+                    .entry(path.clone())
+                    .or_default()
+                    .extend(start..start + count);
+            }
+        }
+
+        Self {
+            files,
+            staged: HashMap::new(),
+            lines_only: false,
+        }
+    }
+
+    pub fn includes_file(&self, path: &Path) -> bool {
+        self.files.contains_key(&normalise(path))
+    }
+
+    /// The staged contents of `path` to lint instead of the file's, with
+    /// `--staged`.
+    pub fn get_staged(&self, path: &Path) -> Option<&str> {
+        self.staged.get(&normalise(path)).map(String::as_str)
+    }
+
+    /// Whether `diagnostic` should be reported: it must be in a changed file,
+    /// and on a changed line if only changed lines are reported.
+    pub fn includes(&self, diagnostic: &Diagnostic) -> bool {
+        match self.files.get(&normalise(diagnostic.get_path())) {
+            Some(lines) => !self.lines_only || lines.contains(&diagnostic.get_line()),
+            None => false,
+        }
+    }
+}
+
+fn git(project_root: &str, args: &[&str]) -> Result<String> {
+    let output = match Command::new("git")
+        .current_dir(project_root)
+        .args(args)
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Err(format!("could not run command 'git {}'", args.join(" ")).into()),
+    };
+    if !output.status.success() {
+        return Err(format!(
+            "'git {}' failed: {}",
+            args.join(" "),
+            str::from_utf8(&output.stderr)?.trim()
+        )
+        .into());
+    }
+
+    Ok(String::from(str::from_utf8(&output.stdout)?))
+}
+
+/// Canonical form of `path` so paths built from the .env and from git compare
+/// equal, or `path` itself if it doesn't exist.
+fn normalise(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{git, ChangeSet, ChangeSource};
+    use crate::Diagnostic;
+
+    const DIFF: &str = "diff --git a/steps/A.java b/steps/A.java\n\
+                        index 1..2 100644\n\
+                        --- a/steps/A.java\n\
+                        +++ b/steps/A.java\n\
+                        @@ -3 +3 @@ class A {\n\
+                        -    a();\n\
+                        +    b();\n\
+                        @@ -10,0 +11,2 @@\n\
+                        +++ not a header\n\
+                        +    d();\n\
+                        @@ -20,2 +22,0 @@\n\
+                        diff --git a/steps/Gone.java b/steps/Gone.java\n\
+                        --- a/steps/Gone.java\n\
+                        +++ /dev/null\n";
+
+    fn diagnostic_on(path: &str, line: usize) -> Diagnostic {
+        Diagnostic::new("rule", Path::new(path), line, 1, "", "")
+    }
+
+    #[test]
+    fn parse_diff_reads_changed_files_and_lines() {
+        let mut change_set = ChangeSet::parse_diff(Path::new("/repo"), DIFF);
+
+        assert!(change_set.includes_file(Path::new("/repo/steps/A.java")));
+        assert!(!change_set.includes_file(Path::new("/repo/steps/Gone.java")));
+        assert!(change_set.includes(&diagnostic_on("/repo/steps/A.java", 5)));
+
+        change_set.lines_only = true;
+        let lines: Vec<usize> = (1..25)
+            .filter(|&line| change_set.includes(&diagnostic_on("/repo/steps/A.java", line)))
+            .collect();
+        assert_eq!(lines, vec![3, 11, 12]);
+    }
+
+    #[test]
+    fn load_reads_staged_contents_whatever_the_diff_prefixes() {
+        let root =
+            std::env::temp_dir().join(format!("lint_apptester_{}_changes", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let project_root = root.to_str().unwrap();
+        let path = root.join("A.java");
+        fs::write(&path, "class A {}\n").unwrap();
+        for args in [
+            &["init", "--quiet"][..],
+            &["config", "diff.noprefix", "true"],
+            &["add", "A.java"],
+            &[
+                "-c",
+                "user.name=a",
+                "-c",
+                "user.email=a@a",
+                "commit",
+                "--quiet",
+                "-m",
+                "a",
+            ],
+        ] {
+            git(project_root, args).unwrap();
+        }
+
+        fs::write(&path, "class A {}\nclass B {}\n").unwrap();
+        git(project_root, &["add", "A.java"]).unwrap();
+        fs::write(&path, "class C {}\n").unwrap();
+
+        let change_set = ChangeSet::load(project_root, &ChangeSource::Staged, true).unwrap();
+        assert!(change_set.includes_file(&path));
+        assert!(change_set.includes(&diagnostic_on(path.to_str().unwrap(), 2)));
+        assert_eq!(
+            change_set.get_staged(&path),
+            Some("class A {}\nclass B {}\n")
+        );
+
+        let change_set = ChangeSet::load(
+            project_root,
+            &ChangeSource::Since(String::from("HEAD")),
+            true,
+        )
+        .unwrap();
+        assert!(change_set.includes(&diagnostic_on(path.to_str().unwrap(), 1)));
+        assert_eq!(change_set.get_staged(&path), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
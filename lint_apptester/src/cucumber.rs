@@ -0,0 +1,395 @@
+//! Cucumber step definitions (`@Given("...")` and friends) and matching them
+//! against the steps written in feature files.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::{
+    java::{Token, TokenKind},
+    DirType, Result, SourceFile, Subdir,
+};
+
+const STEP_ANNOTATIONS: [&str; 5] = ["Given", "When", "Then", "And", "But"];
+
+#[derive(Debug)]
+pub struct StepDefinition {
+    expression: String,
+    pattern: std::result::Result<Regex, String>,
+    path: PathBuf,
+    line: usize,
+    column: usize,
+}
+
+/// Every step definition and feature step under the features and steps
+/// paths, shared by the rules that cross reference them. The files it's built
+/// from are kept, so linting them doesn't read and parse them again.
+#[derive(Default)]
+pub struct StepIndex {
+    definitions: Vec<StepDefinition>,
+    feature_steps: Vec<String>,
+    files: HashMap<PathBuf, (String, SourceFile)>,
+}
+
+/// The [`StepIndex`] shared by every feature's [`Project`](crate::Project), so
+/// steps defined in a common steps dir or for another feature count too. It's
+/// built the first time it's asked for.
+#[derive(Default)]
+pub struct SharedStepIndex {
+    dirs: Vec<Subdir>,
+    follow_symlinks: bool,
+    index: Mutex<Option<Arc<StepIndex>>>,
+}
+
+impl StepDefinition {
+    /// The expression as written in the annotation.
+    pub fn get_expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The compiled expression, or why it could not be compiled.
+    pub fn get_pattern(&self) -> std::result::Result<&Regex, &str> {
+        self.pattern.as_ref().map_err(|err| err.as_str())
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn matches(&self, step_text: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_ok_and(|pattern| pattern.is_match(step_text))
+    }
+}
+
+impl StepIndex {
+    /// Directory types whose files the index is built from.
+    pub const DIR_TYPES: [DirType; 2] = [DirType::Features, DirType::Steps];
+
+    /// Reads the feature and step definition files in `dirs`. Files that can't
+    /// be read are left out; the rules that lint them report any problems.
+    pub fn build(dirs: &[Subdir], follow_symlinks: bool) -> Self {
+        let mut paths = Vec::new();
+        for dir in dirs {
+            if let Ok(dir_paths) = dir.get_files(follow_symlinks) {
+                paths.extend(
+                    dir_paths
+                        .into_iter()
+                        .map(|path| (path, *dir.get_subdir_type())),
+                );
+            }
+        }
+
+        let file_indexes: Vec<Self> = paths
+            .into_par_iter()
+            .filter_map(|(path, dir_type)| {
+                let contents = fs::read_to_string(&path).ok()?;
+                let file = SourceFile::from_contents(&path, dir_type, &contents);
+                let mut index = Self::default();
+                index.add_file(&file);
+                index.files.insert(path, (contents, file));
+                Some(index)
+            })
+            .collect();
+
+        let mut index = Self::default();
+        for file_index in file_indexes {
+            index.definitions.extend(file_index.definitions);
+            index.feature_steps.extend(file_index.feature_steps);
+            index.files.extend(file_index.files);
+        }
+        index
+    }
+
+    /// The contents and parsed form of a file the index was built from.
+    pub fn get_file(&self, path: &Path) -> Option<(&str, &SourceFile)> {
+        self.files
+            .get(path)
+            .map(|(contents, file)| (contents.as_str(), file))
+    }
+
+    pub fn add_file(&mut self, file: &SourceFile) {
+        self.definitions.extend(parse_step_definitions(file));
+
+        let Some(feature) = file
+            .get_gherkin()
+            .and_then(|document| document.get_feature())
+        else {
+            return;
+        };
+        for scenario in feature
+            .get_background()
+            .into_iter()
+            .chain(feature.get_scenarios())
+        {
+            for step in scenario.get_steps() {
+                self.feature_steps.extend(scenario.expand_step(step));
+            }
+        }
+    }
+
+    pub fn matching_definitions(&self, step_text: &str) -> Vec<&StepDefinition> {
+        self.definitions
+            .iter()
+            .filter(|definition| definition.matches(step_text))
+            .collect()
+    }
+
+    /// Whether any feature step runs `definition`.
+    pub fn is_used(&self, definition: &StepDefinition) -> bool {
+        self.feature_steps
+            .iter()
+            .any(|step_text| definition.matches(step_text))
+    }
+}
+
+impl SharedStepIndex {
+    pub fn new(dirs: Vec<Subdir>, follow_symlinks: bool) -> Self {
+        Self {
+            dirs,
+            follow_symlinks,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// The index of every feature and step definition under the features and
+    /// steps paths of `project_root`, whichever feature they belong to.
+    pub fn for_project_root(project_root: &str, follow_symlinks: bool) -> Result<Self> {
+        let mut dirs = Vec::new();
+        for dir_type in StepIndex::DIR_TYPES {
+            let base_path = std::env::var(dir_type.path_var())?;
+            if let Ok(dir) = Subdir::new(format!("{project_root}{base_path}"), dir_type) {
+                dirs.push(dir);
+            }
+        }
+
+        Ok(Self::new(dirs, follow_symlinks))
+    }
+
+    /// The directories the index is built from.
+    pub fn get_dirs(&self) -> &[Subdir] {
+        &self.dirs
+    }
+
+    /// The index, built if it hasn't been. It's built without holding the lock,
+    /// as building runs on the thread pool, which could run a task asking for it
+    /// on the same thread.
+    pub fn get(&self) -> Arc<StepIndex> {
+        if let Some(index) = self.index.lock().unwrap().as_ref() {
+            return Arc::clone(index);
+        }
+
+        let built = Arc::new(StepIndex::build(&self.dirs, self.follow_symlinks));
+        Arc::clone(self.index.lock().unwrap().get_or_insert(built))
+    }
+
+    pub fn set(&self, index: StepIndex) {
+        *self.index.lock().unwrap() = Some(Arc::new(index));
+    }
+
+    /// Drops the index so it's built again after step definitions or features
+    /// change.
+    pub fn reset(&self) {
+        self.index.lock().unwrap().take();
+    }
+}
+
+/// Step definition annotations in a Java file, e.g. `@When("I tap {string}")`.
+pub fn parse_step_definitions(file: &SourceFile) -> Vec<StepDefinition> {
+    let tokens: Vec<&Token> = file.code_tokens().collect();
+    tokens
+        .windows(5)
+        .filter(|window| {
+            window[0].is_punctuation("@")
+                && window[1].get_kind() == TokenKind::Identifier
+                && STEP_ANNOTATIONS.contains(&window[1].get_text())
+                && window[2].is_punctuation("(")
+                && window[3].get_kind() == TokenKind::StringLiteral
+                && window[4].is_punctuation(")")
+        })
+        .map(|window| {
+            let expression = unescape_java_string(window[3].get_text());
+            StepDefinition {
+                pattern: expression_to_regex(&expression),
+                expression,
+                path: file.get_path().to_path_buf(),
+                line: window[0].get_line(),
+                column: window[0].get_column(),
+            }
+        })
+        .collect()
+}
+
+/// Compiles a step expression. Like Cucumber, expressions anchored with `^` or
+/// `$` are regular expressions and anything else is a Cucumber Expression.
+pub fn expression_to_regex(expression: &str) -> std::result::Result<Regex, String> {
+    let pattern = if expression.starts_with('^') || expression.ends_with('$') {
+        let start = if expression.starts_with('^') { "" } else { "^" };
+        let end = if expression.ends_with('$') { "" } else { "$" };
+        format!("{start}{expression}{end}")
+    } else {
+        let words: Vec<String> = expression
+            .split(' ')
+            .map(|word| {
+                let alternatives = split_unescaped(word, '/');
+                if alternatives.len() == 1 {
+                    convert_cucumber_text(word)
+                } else {
+                    let alternatives: Vec<String> = alternatives
+                        .iter()
+                        .map(|alternative| convert_cucumber_text(alternative))
+                        .collect();
+                    format!("(?:{})", alternatives.join("|"))
+                }
+            })
+            .collect();
+        format!("^{}$", words.join(" "))
+    };
+
+    Regex::new(&pattern).map_err(|err| err.to_string())
+}
+
+fn split_unescaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            parts.last_mut().unwrap().push(c);
+            if let Some(escaped) = chars.next() {
+                parts.last_mut().unwrap().push(escaped);
+            }
+        } else if c == separator {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    parts
+}
+
+fn convert_cucumber_text(text: &str) -> String {
+    let mut pattern = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    pattern.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            '{' => {
+                let parameter_type: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                pattern.push_str(parameter_regex(&parameter_type));
+            }
+            '(' => {
+                let optional: String = chars.by_ref().take_while(|&c| c != ')').collect();
+                pattern.push_str(&format!("(?:{})?", regex::escape(&optional)));
+            }
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern
+}
+
+fn parameter_regex(parameter_type: &str) -> &'static str {
+    match parameter_type {
+        "int" | "long" | "short" | "byte" | "biginteger" => r"(-?\d+)",
+        "float" | "double" | "bigdecimal" => r"(-?\d*[.,]?\d+)",
+        "word" => r"(\S+)",
+        "string" => r#"("(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*')"#,
+        // Anonymous `{}` and custom parameter types
+        _ => "(.*)",
+    }
+}
+
+fn unescape_java_string(literal: &str) -> String {
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|literal| literal.strip_suffix('"'))
+        .unwrap_or(literal);
+
+    let mut unescaped = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{expression_to_regex, parse_step_definitions};
+    use crate::{DirType, SourceFile};
+
+    #[test]
+    fn cucumber_expressions_match_steps() {
+        let pattern =
+            expression_to_regex("I have {int} file(s) in my folder/directory {string}").unwrap();
+
+        assert!(pattern.is_match("I have 1 file in my folder \"Work\""));
+        assert!(pattern.is_match("I have 12 files in my directory 'Home'"));
+        assert!(!pattern.is_match("I have one file in my folder \"Work\""));
+        assert!(!pattern.is_match("Then I have 1 file in my folder \"Work\""));
+    }
+
+    #[test]
+    fn regular_expressions_are_anchored() {
+        let pattern = expression_to_regex(r"^I tap (\w+)").unwrap();
+
+        assert!(pattern.is_match("I tap OK"));
+        assert!(!pattern.is_match("I tap OK twice"));
+        assert!(expression_to_regex("^I (open").is_err());
+    }
+
+    #[test]
+    fn parse_step_definitions_reads_annotations() {
+        let file = SourceFile::from_contents(
+            Path::new("Steps.java"),
+            DirType::Steps,
+            "public class Steps {\n\
+             \x20   // @Given(\"commented out\")\n\
+             \x20   @Given(\"I open the \\\"files\\\" screen\")\n\
+             \x20   public void open() {}\n\
+             \n\
+             \x20   @Then(\"^I see (\\\\d+) files$\")\n\
+             \x20   public void see(int count) {}\n\
+             }\n",
+        );
+        let definitions = parse_step_definitions(&file);
+
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(
+            definitions[0].get_expression(),
+            "I open the \"files\" screen"
+        );
+        assert_eq!(definitions[0].get_line(), 3);
+        assert_eq!(definitions[0].get_column(), 4);
+        assert!(definitions[1].matches("I see 3 files"));
+    }
+}
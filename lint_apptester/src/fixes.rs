@@ -0,0 +1,276 @@
+//! Machine applicable edits that rules attach to their diagnostics, and applying
+//! them for `--fix` and `--fix-dry-run`.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::{Diagnostic, Result};
+
+/// Replaces the text between two positions. Lines are numbered from 1 and
+/// columns are byte offsets within the line, like [`crate::java::Token`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edit {
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    replacement: String,
+}
+
+/// The edits that fix one diagnostic. They're applied all together or not at all.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    description: String,
+    edits: Vec<Edit>,
+}
+
+/// A file's contents before and after applying fixes.
+#[derive(Debug)]
+pub struct FixedFile {
+    path: PathBuf,
+    original: String,
+    fixed: String,
+    applied: usize,
+    skipped: usize,
+}
+
+impl Edit {
+    pub fn replace(
+        line: usize,
+        column: usize,
+        end_line: usize,
+        end_column: usize,
+        replacement: &str,
+    ) -> Self {
+        Self {
+            line,
+            column,
+            end_line,
+            end_column,
+            replacement: String::from(replacement),
+        }
+    }
+
+    pub fn insert(line: usize, column: usize, text: &str) -> Self {
+        Self::replace(line, column, line, column, text)
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn get_end_line(&self) -> usize {
+        self.end_line
+    }
+
+    pub fn get_end_column(&self) -> usize {
+        self.end_column
+    }
+
+    pub fn get_replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    /// Byte range of the edit in `contents`.
+    fn range(&self, line_starts: &[usize], contents: &str) -> (usize, usize) {
+        let offset = |line: usize, column: usize| {
+            line_starts
+                .get(line - 1)
+                .map_or(contents.len(), |start| start + column)
+                .min(contents.len())
+        };
+        (
+            offset(self.line, self.column),
+            offset(self.end_line, self.end_column),
+        )
+    }
+}
+
+impl Fix {
+    pub fn new(description: &str, edits: Vec<Edit>) -> Self {
+        Self {
+            description: String::from(description),
+            edits,
+        }
+    }
+
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn get_edits(&self) -> &Vec<Edit> {
+        &self.edits
+    }
+}
+
+impl FixedFile {
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_fixed(&self) -> &str {
+        &self.fixed
+    }
+
+    /// Number of fixes applied.
+    pub fn get_applied(&self) -> usize {
+        self.applied
+    }
+
+    /// Number of fixes left out because they overlapped one already applied.
+    pub fn get_skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// A unified diff of the fixes, with the path shown relative to `root`.
+    pub fn diff(&self, root: &Path) -> String {
+        let path = self.path.strip_prefix(root).unwrap_or(&self.path);
+        let path = path.display();
+        TextDiff::from_lines(&self.original, &self.fixed)
+            .unified_diff()
+            .header(&format!("a/{path}"), &format!("b/{path}"))
+            .to_string()
+    }
+
+    pub fn write(&self) -> Result<()> {
+        fs::write(&self.path, &self.fixed)?;
+        Ok(())
+    }
+}
+
+/// Applies the fixes of `diagnostics` to a copy of each file they're in. Files
+/// are only read here; call [`FixedFile::write`] to save them.
+pub fn fix_files(diagnostics: &[Diagnostic]) -> Result<Vec<FixedFile>> {
+    let mut fixes_by_path: BTreeMap<&Path, Vec<&Fix>> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        if let Some(fix) = diagnostic.get_fix() {
+            fixes_by_path
+                .entry(diagnostic.get_path())
+                .or_default()
+                .push(fix);
+        }
+    }
+
+    let mut fixed_files = Vec::new();
+    for (path, fixes) in fixes_by_path {
+        let original = fs::read_to_string(path)?;
+        let (fixed, applied, skipped) = apply_fixes(&original, &fixes);
+        fixed_files.push(FixedFile {
+            path: path.to_path_buf(),
+            original,
+            fixed,
+            applied,
+            skipped,
+        });
+    }
+
+    Ok(fixed_files)
+}
+
+/// Applies `fixes` to `contents` in order of position, returning the new
+/// contents and how many fixes were applied and skipped. A fix is skipped if
+/// any of its edits overlaps an edit already accepted, except that identical
+/// edits (e.g. two fixes adding the same import) are shared.
+pub fn apply_fixes(contents: &str, fixes: &[&Fix]) -> (String, usize, usize) {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(contents.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+
+    let mut fixes: Vec<Vec<(usize, usize, &str)>> = fixes
+        .iter()
+        .map(|fix| {
+            fix.get_edits()
+                .iter()
+                .map(|edit| {
+                    let (start, end) = edit.range(&line_starts, contents);
+                    (start, end.max(start), edit.get_replacement())
+                })
+                .collect()
+        })
+        .collect();
+    fixes.sort_by_key(|edits| edits.iter().map(|(start, _, _)| *start).min());
+
+    let mut accepted: Vec<(usize, usize, &str)> = Vec::new();
+    let (mut applied, mut skipped) = (0, 0);
+    for edits in fixes {
+        let conflicts = edits.iter().any(|edit| {
+            !accepted.contains(edit)
+                && accepted
+                    .iter()
+                    .any(|accepted_edit| overlaps(*edit, *accepted_edit))
+        });
+        if conflicts {
+            skipped += 1;
+            continue;
+        }
+        for edit in edits {
+            if !accepted.contains(&edit) {
+                accepted.push(edit);
+            }
+        }
+        applied += 1;
+    }
+
+    accepted.sort_by_key(|(start, end, _)| (*start, *end));
+    let mut fixed = String::with_capacity(contents.len());
+    let mut position = 0;
+    for (start, end, replacement) in accepted {
+        fixed.push_str(&contents[position..start]);
+        fixed.push_str(replacement);
+        position = end;
+    }
+    fixed.push_str(&contents[position..]);
+
+    (fixed, applied, skipped)
+}
+
+fn overlaps(a: (usize, usize, &str), b: (usize, usize, &str)) -> bool {
+    let ((a_start, a_end, _), (b_start, b_end, _)) = (a, b);
+    if a_start == a_end && b_start == b_end {
+        // Two insertions at the same place would have an ambiguous order
+        return a_start == b_start;
+    }
+    if a_start == a_end {
+        return b_start < a_start && a_start < b_end;
+    }
+    if b_start == b_end {
+        return a_start < b_start && b_start < a_end;
+    }
+    a_start < b_end && b_start < a_end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_fixes, Edit, Fix};
+
+    #[test]
+    fn apply_fixes_shares_identical_edits_and_skips_overlaps() {
+        let contents = "import a.B;\nclass C {\n    x(1);\n    x(2);\n}\n";
+        let import = Edit::insert(1, 11, "\nimport a.D;");
+        let first = Fix::new(
+            "first",
+            vec![Edit::replace(3, 4, 3, 5, "y"), import.clone()],
+        );
+        let second = Fix::new("second", vec![Edit::replace(4, 4, 4, 5, "y"), import]);
+        let overlapping = Fix::new("overlapping", vec![Edit::replace(3, 4, 3, 8, "z")]);
+
+        assert_eq!(
+            apply_fixes(contents, &[&second, &overlapping, &first]),
+            (
+                String::from("import a.B;\nimport a.D;\nclass C {\n    y(1);\n    y(2);\n}\n"),
+                2,
+                1
+            )
+        );
+    }
+}
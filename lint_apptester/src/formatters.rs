@@ -0,0 +1,561 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use colored::Colorize;
+use serde_json::json;
+
+use crate::{count_severity, Diagnostic, OutputFormat, Report, Severity};
+
+/// Renders a [`Report`] in one output format.
+pub trait Formatter {
+    fn format(&self, report: &Report) -> String;
+}
+
+pub struct TextFormatter;
+pub struct JsonFormatter;
+pub struct SarifFormatter;
+pub struct JunitFormatter;
+pub struct CheckstyleFormatter;
+/// One line per diagnostic, for redrawing the terminal in `--watch` mode.
+pub struct CompactFormatter;
+
+pub fn get_formatter(format: OutputFormat) -> Box<dyn Formatter> {
+    match format {
+        OutputFormat::Text => Box::new(TextFormatter),
+        OutputFormat::Json => Box::new(JsonFormatter),
+        OutputFormat::Sarif => Box::new(SarifFormatter),
+        OutputFormat::Junit => Box::new(JunitFormatter),
+        OutputFormat::Checkstyle => Box::new(CheckstyleFormatter),
+        OutputFormat::Compact => Box::new(CompactFormatter),
+    }
+}
+
+impl Formatter for TextFormatter {
+    fn format(&self, report: &Report) -> String {
+        let mut output = String::new();
+
+        for feature in report.get_features() {
+            if report.get_all_features() {
+                let header = format!("== {} ==", feature.get_feature());
+                writeln!(output, "{}", header.bold()).unwrap();
+            }
+
+            for subdir in feature.get_subdirs() {
+                writeln!(
+                    output,
+                    "{:?} ({}):",
+                    subdir.get_dir_type(),
+                    subdir.get_path().display()
+                )
+                .unwrap();
+                if subdir.get_rules().is_empty() {
+                    writeln!(output, "  # No rules for this directory").unwrap();
+                }
+
+                for rule in subdir.get_rules() {
+                    let diagnostics = rule.get_diagnostics();
+                    writeln!(
+                        output,
+                        "  - {} ({}): {}",
+                        rule.get_description(),
+                        rule.get_id(),
+                        if diagnostics.is_empty() {
+                            "PASS".green()
+                        } else if count_severity(diagnostics, Severity::Error) > 0 {
+                            "FAIL".red()
+                        } else {
+                            "WARN".yellow()
+                        }
+                    )
+                    .unwrap();
+                    for diagnostic in diagnostics {
+                        writeln!(
+                            output,
+                            "      {}:{}:{}: {}: {}",
+                            diagnostic.get_path().display(),
+                            diagnostic.get_line(),
+                            diagnostic.get_column(),
+                            diagnostic.get_severity().label(),
+                            diagnostic.get_message()
+                        )
+                        .unwrap();
+                        writeln!(output, "        {}", diagnostic.get_text().dimmed()).unwrap();
+                    }
+                }
+            }
+        }
+
+        if report.get_all_features() {
+            writeln!(output, "Summary:").unwrap();
+            for feature in report.get_features() {
+                let problem_count = feature.problem_count();
+                let problems = format!("{problem_count} problem(s)");
+                writeln!(
+                    output,
+                    "  - {}: {}",
+                    feature.get_feature(),
+                    if problem_count == 0 {
+                        problems.green()
+                    } else {
+                        problems.red()
+                    }
+                )
+                .unwrap();
+            }
+        }
+
+        let diagnostics = report.diagnostics();
+        writeln!(
+            output,
+            "{} error(s), {} warning(s) across {} feature(s)",
+            count_severity(&diagnostics, Severity::Error),
+            count_severity(&diagnostics, Severity::Warning),
+            report.get_features().len()
+        )
+        .unwrap();
+        let fixable = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.get_fix().is_some())
+            .count();
+        if fixable > 0 {
+            writeln!(output, "{fixable} problem(s) can be fixed with --fix").unwrap();
+        }
+
+        output
+    }
+}
+
+impl Formatter for JsonFormatter {
+    fn format(&self, report: &Report) -> String {
+        let diagnostics = report.diagnostics();
+        let output = json!({
+            "features": report.get_features(),
+            "summary": {
+                "errors": count_severity(&diagnostics, Severity::Error),
+                "warnings": count_severity(&diagnostics, Severity::Warning),
+                "infos": count_severity(&diagnostics, Severity::Info),
+            },
+        });
+
+        serde_json::to_string_pretty(&output).unwrap()
+    }
+}
+
+impl Formatter for SarifFormatter {
+    fn format(&self, report: &Report) -> String {
+        let mut rules = BTreeMap::new();
+        for rule in report.rules() {
+            rules.entry(rule.get_id()).or_insert_with(|| {
+                json!({
+                    "id": rule.get_id(),
+                    "shortDescription": { "text": rule.get_description() },
+                    "defaultConfiguration": { "level": sarif_level(rule.get_severity()) },
+                })
+            });
+        }
+
+        let results: Vec<_> = report
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "ruleId": diagnostic.get_rule_id(),
+                    "level": sarif_level(diagnostic.get_severity()),
+                    "message": { "text": diagnostic.get_message() },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": relative_path(report, diagnostic.get_path()),
+                                "uriBaseId": "%SRCROOT%",
+                            },
+                            "region": {
+                                "startLine": diagnostic.get_line(),
+                                "startColumn": diagnostic.get_column(),
+                                "snippet": { "text": diagnostic.get_text() },
+                            },
+                        },
+                    }],
+                })
+            })
+            .collect();
+
+        let output = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.into_values().collect::<Vec<_>>(),
+                    },
+                },
+                "originalUriBaseIds": {
+                    "%SRCROOT%": { "uri": format!("file://{}/", report.get_project_root().trim_end_matches('/')) },
+                },
+                "results": results,
+            }],
+        });
+
+        serde_json::to_string_pretty(&output).unwrap()
+    }
+}
+
+impl Formatter for JunitFormatter {
+    fn format(&self, report: &Report) -> String {
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+
+        // One suite per feature subdir, one test case per rule
+        for feature in report.get_features() {
+            for subdir in feature.get_subdirs() {
+                let suite_name = format!("{}.{:?}", feature.get_feature(), subdir.get_dir_type());
+                let failures = subdir
+                    .get_rules()
+                    .iter()
+                    .filter(|rule| is_failure(rule.get_diagnostics()))
+                    .count();
+                writeln!(
+                    output,
+                    "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\">",
+                    escape_xml(&suite_name),
+                    subdir.get_rules().len()
+                )
+                .unwrap();
+
+                for rule in subdir.get_rules() {
+                    let diagnostics = rule.get_diagnostics();
+                    write!(
+                        output,
+                        "    <testcase classname=\"{}\" name=\"{}\"",
+                        escape_xml(&suite_name),
+                        escape_xml(rule.get_id())
+                    )
+                    .unwrap();
+                    if !is_failure(diagnostics) {
+                        output.push_str("/>\n");
+                        continue;
+                    }
+
+                    let details: Vec<String> = diagnostics
+                        .iter()
+                        .map(|diagnostic| {
+                            format!(
+                                "{}:{}:{}: {}: {}",
+                                diagnostic.get_path().display(),
+                                diagnostic.get_line(),
+                                diagnostic.get_column(),
+                                diagnostic.get_severity().label(),
+                                diagnostic.get_message()
+                            )
+                        })
+                        .collect();
+                    writeln!(
+                        output,
+                        ">\n      <failure message=\"{}: {} problem(s)\">{}</failure>\n    </testcase>",
+                        escape_xml(rule.get_description()),
+                        diagnostics.len(),
+                        escape_xml(&details.join("\n"))
+                    )
+                    .unwrap();
+                }
+
+                output.push_str("  </testsuite>\n");
+            }
+        }
+
+        output.push_str("</testsuites>\n");
+        output
+    }
+}
+
+impl Formatter for CheckstyleFormatter {
+    fn format(&self, report: &Report) -> String {
+        let mut files: BTreeMap<&Path, Vec<&Diagnostic>> = BTreeMap::new();
+        let diagnostics = report.diagnostics();
+        for diagnostic in &diagnostics {
+            files
+                .entry(diagnostic.get_path())
+                .or_default()
+                .push(diagnostic);
+        }
+
+        let mut output = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n",
+        );
+        for (path, mut diagnostics) in files {
+            diagnostics.sort_by_key(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()));
+            writeln!(
+                output,
+                "  <file name=\"{}\">",
+                escape_xml(&path.display().to_string())
+            )
+            .unwrap();
+            for diagnostic in diagnostics {
+                writeln!(
+                    output,
+                    "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"{}.{}\"/>",
+                    diagnostic.get_line(),
+                    diagnostic.get_column(),
+                    diagnostic.get_severity().label(),
+                    escape_xml(diagnostic.get_message()),
+                    env!("CARGO_PKG_NAME"),
+                    escape_xml(diagnostic.get_rule_id())
+                )
+                .unwrap();
+            }
+            output.push_str("  </file>\n");
+        }
+
+        output.push_str("</checkstyle>\n");
+        output
+    }
+}
+
+impl Formatter for CompactFormatter {
+    fn format(&self, report: &Report) -> String {
+        let mut output = String::new();
+
+        let mut diagnostics = report.diagnostics();
+        diagnostics.sort_by(|a, b| {
+            (a.get_path(), a.get_line(), a.get_column()).cmp(&(
+                b.get_path(),
+                b.get_line(),
+                b.get_column(),
+            ))
+        });
+        for diagnostic in &diagnostics {
+            let severity = diagnostic.get_severity().label();
+            writeln!(
+                output,
+                "{}:{}:{}: {}: {} ({})",
+                relative_path(report, diagnostic.get_path()),
+                diagnostic.get_line(),
+                diagnostic.get_column(),
+                match diagnostic.get_severity() {
+                    Severity::Error => severity.red(),
+                    Severity::Warning => severity.yellow(),
+                    Severity::Info => severity.normal(),
+                },
+                diagnostic.get_message(),
+                diagnostic.get_rule_id().dimmed()
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "{} error(s), {} warning(s)",
+            count_severity(&diagnostics, Severity::Error),
+            count_severity(&diagnostics, Severity::Warning)
+        )
+        .unwrap();
+
+        output
+    }
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+/// Info diagnostics are reported but do not fail a test case.
+fn is_failure(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.get_severity() != Severity::Info)
+}
+
+fn relative_path(report: &Report, path: &Path) -> String {
+    path.strip_prefix(report.get_project_root())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use serde_json::Value;
+
+    use super::{
+        escape_xml, CheckstyleFormatter, Formatter, JsonFormatter, JunitFormatter, SarifFormatter,
+    };
+    use crate::{Diagnostic, DirType, FeatureReport, Report, RuleReport, Severity, SubdirReport};
+
+    fn diagnostic(rule_id: &str, path: &str, line: usize, severity: Severity) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(rule_id, Path::new(path), line, 4, "a();", "<bad>");
+        diagnostic.set_severity(severity);
+        diagnostic
+    }
+
+    fn rule(id: &str, severity: Severity, diagnostics: Vec<Diagnostic>) -> RuleReport {
+        RuleReport {
+            id: String::from(id),
+            description: format!("{id} description"),
+            severity,
+            diagnostics,
+        }
+    }
+
+    /// Four rules in one subdir: two failing, one passing and one with only
+    /// an info diagnostic.
+    fn report() -> Report {
+        let mut feature_report = FeatureReport::new("files");
+        feature_report.add_subdir(SubdirReport {
+            dir_type: DirType::Steps,
+            path: PathBuf::from("/repo/steps/files"),
+            rules: vec![
+                rule(
+                    "a",
+                    Severity::Error,
+                    vec![
+                        diagnostic("a", "/repo/steps/files/A.java", 7, Severity::Error),
+                        diagnostic("a", "/repo/steps/files/A.java", 2, Severity::Error),
+                    ],
+                ),
+                rule(
+                    "b",
+                    Severity::Warning,
+                    vec![diagnostic(
+                        "b",
+                        "/repo/steps/files/B.java",
+                        3,
+                        Severity::Warning,
+                    )],
+                ),
+                rule("c", Severity::Error, Vec::new()),
+                rule(
+                    "d",
+                    Severity::Info,
+                    vec![diagnostic(
+                        "d",
+                        "/repo/steps/files/B.java",
+                        1,
+                        Severity::Info,
+                    )],
+                ),
+            ],
+        });
+        let mut report = Report::new("/repo/", false);
+        report.add_feature(feature_report);
+        report
+    }
+
+    #[test]
+    fn json_lists_rules_and_counts_severities() {
+        let output: Value = serde_json::from_str(&JsonFormatter.format(&report())).unwrap();
+
+        assert_eq!(output["summary"]["errors"], 2);
+        assert_eq!(output["summary"]["warnings"], 1);
+        assert_eq!(output["summary"]["infos"], 1);
+        let rules = &output["features"][0]["subdirs"][0]["rules"];
+        assert_eq!(rules.as_array().unwrap().len(), 4);
+        assert_eq!(rules[0]["id"], "a");
+        assert_eq!(rules[0]["diagnostics"][1]["line"], 2);
+    }
+
+    #[test]
+    fn sarif_has_a_result_per_diagnostic_with_relative_locations() {
+        let output: Value = serde_json::from_str(&SarifFormatter.format(&report())).unwrap();
+
+        let run = &output["runs"][0];
+        let rule_ids: Vec<&str> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rule| rule["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rule_ids, vec!["a", "b", "c", "d"]);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        let result_ids: Vec<&str> = results
+            .iter()
+            .map(|result| result["ruleId"].as_str().unwrap())
+            .collect();
+        assert_eq!(result_ids, vec!["a", "a", "b", "d"]);
+        assert_eq!(results[3]["level"], "note");
+
+        let location = &results[2]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "steps/files/B.java");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["startColumn"], 4);
+        assert_eq!(
+            run["originalUriBaseIds"]["%SRCROOT%"]["uri"],
+            "file:///repo/"
+        );
+    }
+
+    #[test]
+    fn junit_has_a_test_case_per_rule_failing_on_warnings_and_errors() {
+        let output = JunitFormatter.format(&report());
+
+        assert!(output.contains("<testsuite name=\"files.Steps\" tests=\"4\" failures=\"2\">"));
+        assert_eq!(output.matches("<testcase ").count(), 4);
+        assert_eq!(output.matches("<failure ").count(), 2);
+        assert!(output.contains("<testcase classname=\"files.Steps\" name=\"c\"/>"));
+        assert!(output.contains("<testcase classname=\"files.Steps\" name=\"d\"/>"));
+        assert!(output.contains("<failure message=\"a description: 2 problem(s)\">"));
+        assert!(output.contains("A.java:7:4: error: &lt;bad&gt;"));
+    }
+
+    #[test]
+    fn checkstyle_groups_errors_by_file_in_line_order() {
+        let output = CheckstyleFormatter.format(&report());
+
+        let files: Vec<&str> = output
+            .lines()
+            .filter(|line| line.trim_start().starts_with("<file "))
+            .map(str::trim)
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                "<file name=\"/repo/steps/files/A.java\">",
+                "<file name=\"/repo/steps/files/B.java\">"
+            ]
+        );
+
+        let errors: Vec<&str> = output
+            .lines()
+            .filter(|line| line.trim_start().starts_with("<error "))
+            .map(str::trim)
+            .collect();
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with(
+            "<error line=\"2\" column=\"4\" severity=\"error\" message=\"&lt;bad&gt;\""
+        ));
+        assert!(errors[1].starts_with("<error line=\"7\" column=\"4\" severity=\"error\""));
+        assert!(errors[2].starts_with("<error line=\"1\" column=\"4\" severity=\"info\""));
+        assert!(errors[3].ends_with(
+            "severity=\"warning\" message=\"&lt;bad&gt;\" source=\"lint_apptester.b\"/>"
+        ));
+    }
+
+    #[test]
+    fn escape_xml_escapes_markup() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! A line based parser for the parts of Gherkin that rules care about:
+//! features, backgrounds, scenarios and their steps.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKeyword {
+    Given,
+    When,
+    Then,
+    And,
+    But,
+    /// `*`, which continues the previous step like `And`
+    Star,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScenarioKind {
+    Background,
+    Scenario,
+    ScenarioOutline,
+}
+
+#[derive(Debug, Default)]
+pub struct GherkinDocument {
+    feature: Option<Feature>,
+}
+
+#[derive(Debug)]
+pub struct Feature {
+    name: String,
+    line: usize,
+    column: usize,
+    background: Option<Scenario>,
+    scenarios: Vec<Scenario>,
+}
+
+#[derive(Debug)]
+pub struct Scenario {
+    kind: ScenarioKind,
+    name: String,
+    line: usize,
+    column: usize,
+    steps: Vec<Step>,
+    examples: Vec<Examples>,
+}
+
+/// One `Examples:` table of a Scenario Outline.
+#[derive(Debug, Default)]
+pub struct Examples {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub struct Step {
+    keyword: StepKeyword,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+const STEP_KEYWORDS: [(&str, StepKeyword); 6] = [
+    ("Given ", StepKeyword::Given),
+    ("When ", StepKeyword::When),
+    ("Then ", StepKeyword::Then),
+    ("And ", StepKeyword::And),
+    ("But ", StepKeyword::But),
+    ("* ", StepKeyword::Star),
+];
+
+const SCENARIO_KEYWORDS: [(&str, ScenarioKind); 5] = [
+    ("Background:", ScenarioKind::Background),
+    ("Scenario Outline:", ScenarioKind::ScenarioOutline),
+    ("Scenario Template:", ScenarioKind::ScenarioOutline),
+    ("Scenario:", ScenarioKind::Scenario),
+    ("Example:", ScenarioKind::Scenario),
+];
+
+impl StepKeyword {
+    /// Whether the keyword continues the previous step's keyword.
+    pub fn is_conjunction(&self) -> bool {
+        matches!(
+            self,
+            StepKeyword::And | StepKeyword::But | StepKeyword::Star
+        )
+    }
+}
+
+impl GherkinDocument {
+    pub fn parse(source: &str) -> Self {
+        let mut feature: Option<Feature> = None;
+        let mut scenario: Option<Scenario> = None;
+        let mut in_examples = false;
+        let mut doc_string_delimiter: Option<&str> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let trimmed = line.trim();
+            let column = line.len() - line.trim_start().len();
+
+            if let Some(delimiter) = doc_string_delimiter {
+                if trimmed.starts_with(delimiter) {
+                    doc_string_delimiter = None;
+                }
+                continue;
+            }
+            if trimmed.starts_with("\"\"\"") || trimmed.starts_with("```") {
+                doc_string_delimiter = Some(&trimmed[..3]);
+                continue;
+            }
+            if in_examples && trimmed.starts_with('|') {
+                if let Some(examples) = scenario
+                    .as_mut()
+                    .and_then(|scenario| scenario.examples.last_mut())
+                {
+                    let cells = parse_table_row(trimmed);
+                    if examples.header.is_empty() {
+                        examples.header = cells;
+                    } else {
+                        examples.rows.push(cells);
+                    }
+                }
+                continue;
+            }
+            if trimmed.is_empty()
+                || trimmed.starts_with('#')
+                || trimmed.starts_with('@')
+                || trimmed.starts_with('|')
+            {
+                continue;
+            }
+
+            if let Some(name) = trimmed.strip_prefix("Feature:") {
+                feature = Some(Feature {
+                    name: String::from(name.trim()),
+                    line: number,
+                    column,
+                    background: None,
+                    scenarios: Vec::new(),
+                });
+                continue;
+            }
+            if trimmed.starts_with("Rule:") {
+                Self::finish_scenario(&mut feature, scenario.take());
+                continue;
+            }
+
+            // Examples tables belong to the outline above them
+            if trimmed.starts_with("Examples:") || trimmed.starts_with("Scenarios:") {
+                in_examples = true;
+                if let Some(scenario) = scenario.as_mut() {
+                    scenario.examples.push(Examples::default());
+                }
+                continue;
+            }
+
+            if let Some((keyword, kind)) = SCENARIO_KEYWORDS
+                .iter()
+                .find(|(keyword, _)| trimmed.starts_with(keyword))
+            {
+                Self::finish_scenario(&mut feature, scenario.take());
+                in_examples = false;
+                scenario = Some(Scenario {
+                    kind: *kind,
+                    name: String::from(trimmed[keyword.len()..].trim()),
+                    line: number,
+                    column,
+                    steps: Vec::new(),
+                    examples: Vec::new(),
+                });
+                continue;
+            }
+
+            if in_examples {
+                continue;
+            }
+            if let (Some(scenario), Some((keyword, step_keyword))) = (
+                scenario.as_mut(),
+                STEP_KEYWORDS
+                    .iter()
+                    .find(|(keyword, _)| trimmed.starts_with(keyword)),
+            ) {
+                scenario.steps.push(Step {
+                    keyword: *step_keyword,
+                    text: String::from(trimmed[keyword.len()..].trim()),
+                    line: number,
+                    column,
+                });
+            }
+            // Anything else is free form description
+        }
+        Self::finish_scenario(&mut feature, scenario);
+
+        Self { feature }
+    }
+
+    fn finish_scenario(feature: &mut Option<Feature>, scenario: Option<Scenario>) {
+        let (Some(feature), Some(scenario)) = (feature.as_mut(), scenario) else {
+            return;
+        };
+        if scenario.kind == ScenarioKind::Background {
+            feature.background = Some(scenario);
+        } else {
+            feature.scenarios.push(scenario);
+        }
+    }
+
+    pub fn get_feature(&self) -> Option<&Feature> {
+        self.feature.as_ref()
+    }
+}
+
+impl Feature {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn get_background(&self) -> Option<&Scenario> {
+        self.background.as_ref()
+    }
+
+    pub fn get_scenarios(&self) -> &Vec<Scenario> {
+        &self.scenarios
+    }
+}
+
+impl Scenario {
+    pub fn get_kind(&self) -> ScenarioKind {
+        self.kind
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn get_steps(&self) -> &Vec<Step> {
+        &self.steps
+    }
+
+    /// The texts `step` runs with: one per Examples row for a Scenario Outline,
+    /// with `<placeholders>` filled in from that row's table, otherwise just
+    /// the step's text.
+    pub fn expand_step(&self, step: &Step) -> Vec<String> {
+        let texts: Vec<String> = self
+            .examples
+            .iter()
+            .flat_map(|examples| {
+                examples.rows.iter().map(|row| {
+                    examples
+                        .header
+                        .iter()
+                        .zip(row)
+                        .fold(step.text.clone(), |text, (name, value)| {
+                            text.replace(&format!("<{name}>"), value)
+                        })
+                })
+            })
+            .collect();
+
+        match texts.is_empty() {
+            true => vec![step.text.clone()],
+            false => texts,
+        }
+    }
+}
+
+fn parse_table_row(row: &str) -> Vec<String> {
+    row.trim()
+        .trim_start_matches('|')
+        .trim_end_matches('|')
+        .split('|')
+        .map(|cell| String::from(cell.trim()))
+        .collect()
+}
+
+impl Step {
+    pub fn get_keyword(&self) -> StepKeyword {
+        self.keyword
+    }
+
+    /// The step text without its keyword.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GherkinDocument, ScenarioKind, StepKeyword};
+
+    #[test]
+    fn parse_reads_feature_structure() {
+        let document = GherkinDocument::parse(
+            "@files\n\
+             Feature: Files\n\
+             \x20 Some description\n\
+             \n\
+             \x20 Background:\n\
+             \x20   Given I am logged in\n\
+             \n\
+             \x20 # A comment\n\
+             \x20 Scenario: Open files\n\
+             \x20   Given I open the files screen\n\
+             \x20   And I wait\n\
+             \x20   Then I should see:\n\
+             \x20     \"\"\"\n\
+             \x20     Then this is not a step\n\
+             \x20     \"\"\"\n\
+             \n\
+             \x20 Scenario Outline: Count <n>\n\
+             \x20   Then I should see <n> files\n\
+             \x20   Examples:\n\
+             \x20     | n |\n\
+             \x20     | 1 |\n",
+        );
+        let feature = document.get_feature().unwrap();
+
+        assert_eq!(feature.get_name(), "Files");
+        assert_eq!(feature.get_line(), 2);
+        assert_eq!(feature.get_background().unwrap().get_steps().len(), 1);
+
+        let scenarios = feature.get_scenarios();
+        assert_eq!(scenarios.len(), 2);
+        assert_eq!(scenarios[0].get_name(), "Open files");
+        let keywords: Vec<StepKeyword> = scenarios[0]
+            .get_steps()
+            .iter()
+            .map(|step| step.get_keyword())
+            .collect();
+        assert_eq!(
+            keywords,
+            vec![StepKeyword::Given, StepKeyword::And, StepKeyword::Then]
+        );
+        assert_eq!(scenarios[0].get_steps()[1].get_text(), "I wait");
+        assert_eq!(scenarios[0].get_steps()[1].get_column(), 4);
+
+        assert_eq!(scenarios[1].get_kind(), ScenarioKind::ScenarioOutline);
+        assert_eq!(scenarios[1].get_steps().len(), 1);
+        assert_eq!(
+            scenarios[1].expand_step(&scenarios[1].get_steps()[0]),
+            vec![String::from("I should see 1 files")]
+        );
+    }
+
+    #[test]
+    fn expand_step_uses_each_examples_table_header() {
+        let document = GherkinDocument::parse(
+            "Feature: Login\n\
+             \x20 Scenario Outline: Log in\n\
+             \x20   Given I log in as <user>\n\
+             \x20   Examples: Admins\n\
+             \x20     | user  |\n\
+             \x20     | admin |\n\
+             \x20   Examples: Guests\n\
+             \x20     | user  | note |\n\
+             \x20     | guest | new  |\n\
+             \x20     | bot   | old  |\n",
+        );
+        let scenario = &document.get_feature().unwrap().get_scenarios()[0];
+
+        assert_eq!(
+            scenario.expand_step(&scenario.get_steps()[0]),
+            vec![
+                String::from("I log in as admin"),
+                String::from("I log in as guest"),
+                String::from("I log in as bot")
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_without_feature_is_empty() {
+        assert!(GherkinDocument::parse("# just a comment\n")
+            .get_feature()
+            .is_none());
+    }
+}
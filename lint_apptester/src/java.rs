@@ -0,0 +1,385 @@
+//! A small tokenizer for Java (and the C-like subset of JavaScript used in test
+//! projects), so rules only match real code and not comments or literals.
+
+const KEYWORDS: [&str; 53] = [
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "try",
+    "void",
+    "volatile",
+    "while",
+    "true",
+    "false",
+    "null",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Identifier,
+    Keyword,
+    StringLiteral,
+    CharLiteral,
+    NumberLiteral,
+    Punctuation,
+    LineComment,
+    BlockComment,
+    DocComment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+/// An `import` declaration, e.g. `import static a.b.C.d;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    path: String,
+    is_static: bool,
+    line: usize,
+    column: usize,
+}
+
+impl TokenKind {
+    pub fn is_comment(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::LineComment | TokenKind::BlockComment | TokenKind::DocComment
+        )
+    }
+}
+
+impl Token {
+    pub fn get_kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Line the token starts on, numbered from 1.
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    /// Byte offset of the token's first character within its line.
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn is(&self, kind: TokenKind, text: &str) -> bool {
+        self.kind == kind && self.text == text
+    }
+
+    pub fn is_punctuation(&self, text: &str) -> bool {
+        self.is(TokenKind::Punctuation, text)
+    }
+
+    /// Identifiers and keywords, i.e. tokens that are words of code.
+    pub fn is_word(&self) -> bool {
+        matches!(self.kind, TokenKind::Identifier | TokenKind::Keyword)
+    }
+}
+
+impl Import {
+    /// The imported name without `import`, `static` or the trailing `;`.
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    position: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source[self.position..].chars().nth(n)
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        self.source[self.position..].starts_with(pattern)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.line_start = self.position;
+        }
+        Some(c)
+    }
+
+    fn bump_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+    }
+
+    /// Consumes up to and including `terminator`, skipping backslash escapes.
+    fn bump_until(&mut self, terminator: &str, escapes: bool) {
+        while self.position < self.source.len() {
+            if self.starts_with(terminator) {
+                for _ in terminator.chars() {
+                    self.bump();
+                }
+                return;
+            }
+            if self.bump() == Some('\\') && escapes {
+                self.bump();
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.bump_while(char::is_whitespace);
+
+        let start = self.position;
+        let line = self.line;
+        let column = start - self.line_start;
+        let c = self.peek()?;
+
+        let kind = if self.starts_with("//") {
+            self.bump_while(|c| c != '\n');
+            TokenKind::LineComment
+        } else if self.starts_with("/**") && !self.starts_with("/**/") {
+            self.bump_until("*/", false);
+            TokenKind::DocComment
+        } else if self.starts_with("/*") {
+            self.bump();
+            self.bump();
+            self.bump_until("*/", false);
+            TokenKind::BlockComment
+        } else if self.starts_with("\"\"\"") {
+            for _ in 0..3 {
+                self.bump();
+            }
+            self.bump_until("\"\"\"", true);
+            TokenKind::StringLiteral
+        } else if c == '"' || c == '`' {
+            self.bump();
+            self.bump_until(&c.to_string(), true);
+            TokenKind::StringLiteral
+        } else if c == '\'' {
+            self.bump();
+            self.bump_until("'", true);
+            TokenKind::CharLiteral
+        } else if c.is_ascii_digit()
+            || (c == '.' && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            self.bump_while(|c| c.is_alphanumeric() || c == '.' || c == '_');
+            TokenKind::NumberLiteral
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            self.bump_while(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            if KEYWORDS.contains(&&self.source[start..self.position]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Identifier
+            }
+        } else {
+            self.bump();
+            TokenKind::Punctuation
+        };
+
+        Some(Token {
+            kind,
+            text: String::from(&self.source[start..self.position]),
+            line,
+            column,
+        })
+    }
+}
+
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer {
+        source,
+        position: 0,
+        line: 1,
+        line_start: 0,
+    };
+
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Import declarations, read from code tokens (i.e. without comments).
+pub fn parse_imports<'a>(tokens: impl IntoIterator<Item = &'a Token>) -> Vec<Import> {
+    let mut imports = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if token.is(TokenKind::Keyword, "class") || token.is(TokenKind::Keyword, "interface") {
+            break;
+        }
+        if !token.is(TokenKind::Keyword, "import") {
+            continue;
+        }
+
+        let is_static = tokens
+            .next_if(|token| token.is(TokenKind::Keyword, "static"))
+            .is_some();
+        let mut path = String::new();
+        for token in tokens.by_ref() {
+            if token.is_punctuation(";") {
+                break;
+            }
+            path.push_str(token.get_text());
+        }
+        imports.push(Import {
+            path,
+            is_static,
+            line: token.get_line(),
+            column: token.get_column(),
+        });
+    }
+
+    imports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_imports, tokenize, TokenKind};
+
+    #[test]
+    fn tokenize_separates_comments_and_literals_from_code() {
+        let tokens = tokenize(
+            "/** Doc with assert */\nclass A { // assert\n  String s = \"assert \\\" here\"; /* a\n b */ char c = '\\'';\n}\n",
+        );
+        let kinds: Vec<(TokenKind, &str)> = tokens
+            .iter()
+            .map(|token| (token.get_kind(), token.get_text()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::DocComment, "/** Doc with assert */"),
+                (TokenKind::Keyword, "class"),
+                (TokenKind::Identifier, "A"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::LineComment, "// assert"),
+                (TokenKind::Identifier, "String"),
+                (TokenKind::Identifier, "s"),
+                (TokenKind::Punctuation, "="),
+                (TokenKind::StringLiteral, "\"assert \\\" here\""),
+                (TokenKind::Punctuation, ";"),
+                (TokenKind::BlockComment, "/* a\n b */"),
+                (TokenKind::Keyword, "char"),
+                (TokenKind::Identifier, "c"),
+                (TokenKind::Punctuation, "="),
+                (TokenKind::CharLiteral, "'\\''"),
+                (TokenKind::Punctuation, ";"),
+                (TokenKind::Punctuation, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_tracks_lines_and_columns() {
+        let tokens = tokenize("/* one\n two */ a\n\n    b.c()");
+        let positions: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|token| (token.get_text(), token.get_line(), token.get_column()))
+            .collect();
+
+        assert_eq!(positions[1], ("a", 2, 8));
+        assert_eq!(positions[2], ("b", 4, 4));
+        assert_eq!(positions[4], ("c", 4, 6));
+    }
+
+    #[test]
+    fn tokenize_handles_text_blocks_and_unterminated_input() {
+        let tokens = tokenize("String s = \"\"\"\n  \"quoted\"\n  \"\"\"; x");
+        assert_eq!(tokens[3].get_kind(), TokenKind::StringLiteral);
+        assert_eq!(tokens[5].get_text(), "x");
+        assert_eq!(tokens[5].get_line(), 3);
+
+        assert_eq!(tokenize("/* never closed").len(), 1);
+        assert_eq!(tokenize("\"never closed").len(), 1);
+    }
+
+    #[test]
+    fn parse_imports_reads_paths() {
+        let tokens = tokenize(
+            "package a;\nimport a.b.C;\n// import x.Y;\nimport static org.junit.Assert.*;\npublic class D { import z; }",
+        );
+        let imports = parse_imports(tokens.iter().filter(|token| !token.get_kind().is_comment()));
+
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].get_path(), "a.b.C");
+        assert!(!imports[0].is_static());
+        assert_eq!(imports[0].get_line(), 2);
+        assert_eq!(imports[1].get_path(), "org.junit.Assert.*");
+        assert!(imports[1].is_static());
+    }
+}
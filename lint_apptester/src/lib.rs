@@ -3,50 +3,277 @@
 extern crate test;
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     env,
     error::Error,
-    fs::{self, File},
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
     process::Command,
     str,
+    sync::Arc,
 };
 
-use colored::Colorize;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use cache::Cache;
+use changes::{ChangeSet, ChangeSource};
+use cucumber::{SharedStepIndex, StepIndex};
+use fixes::Fix;
+use gherkin::GherkinDocument;
+use java::{Import, Token, TokenKind};
+use lint_config::{CustomRuleConfig, LintConfig, RuleConfig};
+use rules::{CustomRule, UnusedSuppression};
+use suppressions::Suppressions;
+
+pub mod baseline;
+pub mod cache;
+pub mod changes;
+pub mod cucumber;
+pub mod fixes;
+pub mod formatters;
+pub mod gherkin;
+pub mod java;
+pub mod lint_config;
+pub mod lsp;
+pub mod suppressions;
+pub mod watch;
 
 pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
-#[derive(Debug, PartialEq)]
+const LINTABLE_EXTENSIONS: [&str; 3] = ["feature", "java", "js"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DirType {
+    #[serde(alias = "features")]
     Features,
+    #[serde(alias = "interactions")]
     Interactions,
+    #[serde(alias = "pages")]
     Pages,
+    #[serde(alias = "steps")]
     Steps,
 }
 
+impl DirType {
+    pub const ALL: [DirType; 4] = [
+        DirType::Features,
+        DirType::Interactions,
+        DirType::Pages,
+        DirType::Steps,
+    ];
+
+    /// Environment variable holding the path, relative to the project root, that
+    /// contains one directory per feature.
+    pub fn path_var(&self) -> &'static str {
+        match self {
+            DirType::Features => "FEATURES_PATH",
+            DirType::Interactions => "INTERACTIONS_PATH",
+            DirType::Pages => "PAGES_PATH",
+            DirType::Steps => "STEPS_PATH",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DirType::Features => "features",
+            DirType::Interactions => "interactions",
+            DirType::Pages => "pages",
+            DirType::Steps => "steps",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// Process exit codes, so CI can tell clean runs, warnings and errors apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Clean = 0,
+    Warnings = 1,
+    Errors = 2,
+    Failure = 3,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    rule_id: String,
+    severity: Severity,
+    path: PathBuf,
+    line: usize,
+    column: usize,
+    text: String,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fix: Option<Fix>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Sarif,
+    Junit,
+    Checkstyle,
+    Compact,
+}
+
+/// What to do with the fixes attached to diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixMode {
+    /// Write the fixed files
+    Apply,
+    /// Print a unified diff of the fixes without changing any files
+    DryRun,
+}
+
 pub struct Config {
     current_dir: String,
-    feature: String,
+    feature: Option<String>,
+    max_warnings: Option<usize>,
+    warnings_as_errors: bool,
+    format: OutputFormat,
+    output_path: Option<PathBuf>,
+    fix_mode: Option<FixMode>,
+    change_source: Option<ChangeSource>,
+    changed_lines_only: bool,
+    baseline_path: Option<PathBuf>,
+    write_baseline: bool,
+    no_cache: bool,
+    watch: bool,
+    lsp: bool,
 }
 
 pub struct Project {
     feature_being_tested: String,
+    follow_symlinks: bool,
+    subdirs: Vec<Subdir>,
+    /// Where each dir type's features live, whether or not this feature has one
+    dir_type_paths: Vec<(DirType, PathBuf)>,
+    step_index: Arc<SharedStepIndex>,
+}
+
+/// A check run against every file in the directory types it applies to.
+pub trait LintRule: Send + Sync {
+    /// Stable identifier used to refer to the rule, e.g. `log-instead-of-sout`.
+    fn id(&self) -> &str;
+
+    /// Human readable summary printed next to the rule's result.
+    fn description(&self) -> &str;
+
+    fn dir_types(&self) -> &[DirType];
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// Whether the rule's diagnostics for a file depend on other files too, so
+    /// they can't be cached or rechecked one file at a time.
+    fn is_cross_file(&self) -> bool {
+        false
+    }
+
+    /// Rule specific keys its `[rules.<id>]` table may set, on top of
+    /// `enabled`, `severity` and `dir_types`.
+    fn options(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Checks the values of the rule's options when the configuration is
+    /// loaded, so a bad value stops the run once rather than in every file.
+    fn check_options(&self, _rule_config: &RuleConfig) -> std::result::Result<(), String> {
+        Ok(())
+    }
+
+    /// Checks that the rule can work with the project's layout, so a rule that
+    /// would never report anything fails the run instead.
+    fn check_project(
+        &self,
+        _project: &Project,
+        _rule_config: Option<&RuleConfig>,
+    ) -> std::result::Result<(), String> {
+        Ok(())
+    }
+
+    /// Whether the rule runs when the configuration doesn't turn it off.
+    fn is_enabled_by_default(&self) -> bool {
+        true
+    }
+
+    /// Whether the rule offers fixes for some of what it reports.
+    fn has_fixes(&self) -> bool {
+        false
+    }
 
-    // ci_runner_subdir: Subdir,
-    features_subdir: Subdir,
-    interactions_subdir: Subdir,
-    pages_subdir: Subdir,
-    steps_subdir: Subdir,
+    fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic>;
 }
 
-pub struct Rule {
-    name: String,
-    rule: fn(&File) -> bool,
-    dir_types: Vec<DirType>,
+pub struct RuleContext<'a> {
+    project: &'a Project,
+    subdir: &'a Subdir,
+    rule_config: Option<&'a RuleConfig>,
 }
 
 pub struct Rules {
-    rules: Vec<Rule>,
+    rules: Vec<Box<dyn LintRule>>,
+    config: LintConfig,
+    /// Problems with the configuration that don't stop the rules running
+    warnings: Vec<String>,
+}
+
+/// Everything found in a run, grouped by feature, subdir and rule. Formatters
+/// in [`formatters`] render it for people or for other tools.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    #[serde(skip)]
+    project_root: String,
+    #[serde(skip)]
+    all_features: bool,
+    features: Vec<FeatureReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureReport {
+    feature: String,
+    subdirs: Vec<SubdirReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubdirReport {
+    dir_type: DirType,
+    path: PathBuf,
+    rules: Vec<RuleReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleReport {
+    id: String,
+    description: String,
+    severity: Severity,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A file read into memory once and shared by every rule that checks it.
+pub struct SourceFile {
+    path: PathBuf,
+    dir_type: DirType,
+    lines: Vec<String>,
+    /// `\r\n` if the file uses it, otherwise `\n`
+    line_ending: &'static str,
+    tokens: Vec<Token>,
+    gherkin: Option<GherkinDocument>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SourceLine<'a> {
+    number: usize,
+    text: &'a str,
 }
 
 pub struct Subdir {
@@ -54,6 +281,130 @@ pub struct Subdir {
     subdir_type: DirType,
 }
 
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "junit" => Ok(OutputFormat::Junit),
+            "checkstyle" => Ok(OutputFormat::Checkstyle),
+            "compact" => Ok(OutputFormat::Compact),
+            _ => Err(format!(
+                "unknown format '{format}', expected text, json, sarif, junit, checkstyle or compact"
+            )),
+        }
+    }
+}
+
+impl ExitStatus {
+    pub fn from_diagnostics(
+        diagnostics: &[Diagnostic],
+        max_warnings: Option<usize>,
+        warnings_as_errors: bool,
+    ) -> Self {
+        let error_count = count_severity(diagnostics, Severity::Error);
+        let warning_count = count_severity(diagnostics, Severity::Warning);
+
+        if error_count > 0
+            || (warnings_as_errors && warning_count > 0)
+            || max_warnings.is_some_and(|max_warnings| warning_count > max_warnings)
+        {
+            ExitStatus::Errors
+        } else if warning_count > 0 {
+            ExitStatus::Warnings
+        } else {
+            ExitStatus::Clean
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+pub fn count_severity(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.get_severity() == severity)
+        .count()
+}
+
+impl Diagnostic {
+    pub fn new(
+        rule_id: &str,
+        path: &Path,
+        line: usize,
+        column: usize,
+        text: &str,
+        message: &str,
+    ) -> Self {
+        Self {
+            rule_id: String::from(rule_id),
+            severity: Severity::Error,
+            path: path.to_path_buf(),
+            line,
+            column,
+            text: String::from(text),
+            message: String::from(message),
+            fix: None,
+        }
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    pub fn get_rule_id(&self) -> &str {
+        &self.rule_id
+    }
+
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn set_severity(&mut self, severity: Severity) {
+        self.severity = severity;
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_fix(&self) -> Option<&Fix> {
+        self.fix.as_ref()
+    }
+}
+
 // TODO: Allow env vars to specify pages, interactions, etc dirs
 impl Config {
     pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config> {
@@ -61,7 +412,102 @@ impl Config {
 
         let current_dir = env::current_dir()?.to_str().unwrap().to_string();
 
-        let dotenv_path = match args.next() {
+        let mut all_features = false;
+        let mut max_warnings = None;
+        let mut warnings_as_errors = false;
+        let mut format = OutputFormat::Text;
+        let mut output_path = None;
+        let mut fix_mode = None;
+        let mut change_source = None;
+        let mut changed_lines_only = false;
+        let mut baseline_path = None;
+        let mut write_baseline = false;
+        let mut no_cache = false;
+        let mut watch = false;
+        let mut lsp = false;
+        let mut positional_args = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--all-features" => all_features = true,
+                "--max-warnings" => {
+                    max_warnings = match args.next().map(|value| value.parse()) {
+                        Some(Ok(value)) => Some(value),
+                        _ => return Err("--max-warnings needs a number".into()),
+                    }
+                }
+                "--warnings-as-errors" => warnings_as_errors = true,
+                "--format" => {
+                    format = match args.next() {
+                        Some(value) => value.parse()?,
+                        None => return Err("--format needs a value".into()),
+                    }
+                }
+                "--output" => {
+                    output_path = match args.next() {
+                        Some(value) => Some(PathBuf::from(value)),
+                        None => return Err("--output needs a file path".into()),
+                    }
+                }
+                "--fix" => fix_mode = Some(FixMode::Apply),
+                "--fix-dry-run" => fix_mode = Some(FixMode::DryRun),
+                "--changed-since" | "--staged" if change_source.is_some() => {
+                    return Err("use only one of --changed-since and --staged".into())
+                }
+                "--changed-since" => {
+                    change_source = match args.next() {
+                        Some(value) => Some(ChangeSource::Since(value)),
+                        None => return Err("--changed-since needs a git ref".into()),
+                    }
+                }
+                "--staged" => change_source = Some(ChangeSource::Staged),
+                "--changed-lines-only" => changed_lines_only = true,
+                "--baseline" => {
+                    baseline_path = match args.next() {
+                        Some(value) => Some(PathBuf::from(value)),
+                        None => return Err("--baseline needs a file path".into()),
+                    }
+                }
+                "--write-baseline" => write_baseline = true,
+                "--no-cache" => no_cache = true,
+                "--watch" => watch = true,
+                "--lsp" => lsp = true,
+                flag if flag.starts_with("--") => {
+                    return Err(format!("unknown flag '{flag}'").into())
+                }
+                _ => positional_args.push(arg),
+            }
+        }
+        if changed_lines_only && change_source.is_none() {
+            return Err("--changed-lines-only needs --changed-since or --staged".into());
+        }
+        if fix_mode.is_some() && change_source == Some(ChangeSource::Staged) {
+            return Err(
+                "--fix and --fix-dry-run can't be used with --staged, which lints the staged contents rather than the files".into(),
+            );
+        }
+        if write_baseline && change_source.is_some() {
+            return Err("--write-baseline needs every file linted, not only changed ones".into());
+        }
+        if watch
+            && (fix_mode.is_some()
+                || change_source.is_some()
+                || write_baseline
+                || output_path.is_some())
+        {
+            return Err("--watch can't be used with --fix, --fix-dry-run, --changed-since, --staged, --write-baseline or --output".into());
+        }
+        if lsp
+            && (watch
+                || fix_mode.is_some()
+                || change_source.is_some()
+                || write_baseline
+                || output_path.is_some())
+        {
+            return Err("--lsp can't be used with --watch, --fix, --fix-dry-run, --changed-since, --staged, --write-baseline or --output".into());
+        }
+        let mut positional_args = positional_args.into_iter();
+
+        let dotenv_path = match positional_args.next() {
             Some(arg) => arg,
             None => return Err("didn't get a .env file path".into()),
         };
@@ -70,9 +516,14 @@ impl Config {
             Err(_) => return Err("could not find .env file".into()),
         };
 
-        let feature = dotenv::var("FEATURE_TO_TEST")?;
+        // Editors open files of any feature
+        let feature = if all_features || lsp {
+            None
+        } else {
+            Some(dotenv::var("FEATURE_TO_TEST")?)
+        };
 
-        let current_dir = match args.next() {
+        let current_dir = match positional_args.next() {
             Some(arg) => arg,
             None => current_dir,
         };
@@ -80,6 +531,18 @@ impl Config {
         Ok(Config {
             current_dir,
             feature,
+            max_warnings,
+            warnings_as_errors,
+            format,
+            output_path,
+            fix_mode,
+            change_source,
+            changed_lines_only,
+            baseline_path,
+            write_baseline,
+            no_cache,
+            watch,
+            lsp,
         })
     }
 
@@ -87,8 +550,62 @@ impl Config {
         &self.current_dir
     }
 
-    pub fn get_feature(&self) -> &str {
-        &self.feature
+    /// The single feature to lint, or `None` when linting every feature.
+    pub fn get_feature(&self) -> Option<&str> {
+        self.feature.as_deref()
+    }
+
+    pub fn get_max_warnings(&self) -> Option<usize> {
+        self.max_warnings
+    }
+
+    pub fn get_warnings_as_errors(&self) -> bool {
+        self.warnings_as_errors
+    }
+
+    pub fn get_format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn get_output_path(&self) -> Option<&Path> {
+        self.output_path.as_deref()
+    }
+
+    pub fn get_fix_mode(&self) -> Option<FixMode> {
+        self.fix_mode
+    }
+
+    /// What to compare against when only linting changed files.
+    pub fn get_change_source(&self) -> Option<&ChangeSource> {
+        self.change_source.as_ref()
+    }
+
+    pub fn get_changed_lines_only(&self) -> bool {
+        self.changed_lines_only
+    }
+
+    /// The baseline file given with `--baseline`, if any.
+    pub fn get_baseline_path(&self) -> Option<&Path> {
+        self.baseline_path.as_deref()
+    }
+
+    pub fn get_write_baseline(&self) -> bool {
+        self.write_baseline
+    }
+
+    /// Whether to lint every file again instead of reading and writing the cache.
+    pub fn get_no_cache(&self) -> bool {
+        self.no_cache
+    }
+
+    /// Whether to keep running and lint again whenever files change.
+    pub fn get_watch(&self) -> bool {
+        self.watch
+    }
+
+    /// Whether to run as a language server over stdio instead of linting once.
+    pub fn get_lsp(&self) -> bool {
+        self.lsp
     }
 }
 
@@ -96,314 +613,1336 @@ impl Project {
     pub fn init(project_root: &str, feature_being_tested: &str) -> Result<Self> {
         let feature_being_tested = feature_being_tested.to_lowercase();
 
-        let features_path_string = std::env::var("FEATURES_PATH")?;
-        let features_subdir_path_string =
-            format!("{project_root}{features_path_string}{feature_being_tested}");
-        let features_subdir = Subdir::new(features_subdir_path_string, DirType::Features)?;
+        let mut subdirs = Vec::new();
+        for dir_type in DirType::ALL {
+            let subdir_path_string =
+                get_subdir_path_string(project_root, dir_type, &feature_being_tested)?;
+            subdirs.push(Subdir::new(subdir_path_string, dir_type)?);
+        }
+        let step_index = SharedStepIndex::for_project_root(project_root, follow_symlinks())?;
+
+        Ok(Self::from_subdirs(
+            feature_being_tested,
+            subdirs,
+            dir_type_paths(project_root)?,
+            Arc::new(step_index),
+        ))
+    }
 
-        let interactions_path_string = std::env::var("INTERACTIONS_PATH")?;
-        let interactions_subdir_path_string =
-            format!("{project_root}{interactions_path_string}{feature_being_tested}");
-        let interactions_subdir =
-            Subdir::new(interactions_subdir_path_string, DirType::Interactions)?;
+    /// Builds a project for every feature directory found under any of the
+    /// dir type paths. Features need not have a directory for every dir type.
+    pub fn discover(project_root: &str) -> Result<Vec<Self>> {
+        let dir_type_paths = dir_type_paths(project_root)?;
+        let mut features = BTreeSet::new();
+        for (_, base_path) in &dir_type_paths {
+            let dir = match fs::read_dir(base_path) {
+                Ok(dir) => dir,
+                Err(_) => return Err(format!("could not locate {}", base_path.display()).into()),
+            };
+            for entry in dir {
+                let entry = entry?;
+                if entry.path().is_dir() {
+                    features.insert(entry.file_name().to_string_lossy().to_lowercase());
+                }
+            }
+        }
 
-        let pages_path_string = std::env::var("PAGES_PATH")?;
-        let pages_subdir_path_string =
-            format!("{project_root}{pages_path_string}{feature_being_tested}");
-        let pages_subdir = Subdir::new(pages_subdir_path_string, DirType::Pages)?;
+        // Steps are matched against every feature, not just their own
+        let step_index = Arc::new(SharedStepIndex::for_project_root(
+            project_root,
+            follow_symlinks(),
+        )?);
+        let mut projects = Vec::new();
+        for feature in features {
+            let mut subdirs = Vec::new();
+            for dir_type in DirType::ALL {
+                let subdir_path_string = get_subdir_path_string(project_root, dir_type, &feature)?;
+                if let Ok(subdir) = Subdir::new(subdir_path_string, dir_type) {
+                    subdirs.push(subdir);
+                }
+            }
+            projects.push(Self::from_subdirs(
+                feature,
+                subdirs,
+                dir_type_paths.clone(),
+                Arc::clone(&step_index),
+            ));
+        }
 
-        let steps_path_string = std::env::var("STEPS_PATH")?;
-        let steps_subdir_path_string =
-            format!("{project_root}{steps_path_string}{feature_being_tested}");
-        let steps_subdir = Subdir::new(steps_subdir_path_string, DirType::Steps)?;
+        Ok(projects)
+    }
 
-        Ok(Self {
+    fn from_subdirs(
+        feature_being_tested: String,
+        subdirs: Vec<Subdir>,
+        dir_type_paths: Vec<(DirType, PathBuf)>,
+        step_index: Arc<SharedStepIndex>,
+    ) -> Self {
+        Self {
             feature_being_tested,
-            features_subdir,
-            interactions_subdir,
-            pages_subdir,
-            steps_subdir,
-        })
+            follow_symlinks: follow_symlinks(),
+            subdirs,
+            dir_type_paths,
+            step_index,
+        }
     }
 
     pub fn get_feature_being_tested(&self) -> &str {
         &self.feature_being_tested
     }
 
-    pub fn get_subdirs(&self) -> Vec<&Subdir> {
-        vec![
-            // &self.ci_runner_subdir,
-            &self.features_subdir,
-            &self.interactions_subdir,
-            &self.pages_subdir,
-            &self.steps_subdir,
-        ]
+    pub fn get_follow_symlinks(&self) -> bool {
+        self.follow_symlinks
     }
-}
 
-impl Rule {
-    pub fn new(name: &str, rule: fn(&File) -> bool, dir_types: Vec<DirType>) -> Self {
-        Self {
-            name: String::from(name),
-            rule,
-            dir_types,
-        }
+    /// The path every dir type's feature directories are in, e.g. `PAGES_PATH`
+    /// under the project root.
+    pub fn get_dir_type_paths(&self) -> &[(DirType, PathBuf)] {
+        &self.dir_type_paths
     }
 
-    pub fn get_name(&self) -> &str {
-        &self.name
+    /// Step definitions and feature steps of every feature, read the first
+    /// time a rule asks.
+    pub fn get_step_index(&self) -> Arc<StepIndex> {
+        self.step_index.get()
     }
 
-    pub fn get_rule(&self) -> &fn(&File) -> bool {
-        &self.rule
+    /// Builds the step index now if an enabled cross-file rule will ask for it,
+    /// so the subdirs linted in parallel don't each start building it.
+    pub fn prepare_step_index(&self, rules: &Rules) {
+        let uses_step_index = rules
+            .get_rules()
+            .iter()
+            .any(|rule| rule.is_cross_file() && rules.is_enabled(rule.as_ref()));
+        if uses_step_index {
+            self.get_step_index();
+        }
     }
 
-    pub fn get_dir_types(&self) -> &Vec<DirType> {
-        &self.dir_types
+    /// The step index, shared with the other features' projects.
+    pub fn get_shared_step_index(&self) -> &SharedStepIndex {
+        &self.step_index
     }
-}
 
-impl Rules {
-    pub fn init() -> Self {
-        Self { rules: Vec::new() }
+    /// Drops the step index, for every project sharing it, so it's read again
+    /// after step definitions or features change.
+    pub fn reset_step_index(&self) {
+        self.step_index.reset();
     }
 
-    pub fn add_rule(&mut self, rule: Rule) {
-        self.rules.push(rule);
+    pub fn get_subdirs(&self) -> Vec<&Subdir> {
+        self.subdirs.iter().collect()
     }
 
-    pub fn get_rules(&self) -> &Vec<Rule> {
-        &self.rules
+    pub fn get_subdir(&self, dir_type: DirType) -> Option<&Subdir> {
+        self.subdirs
+            .iter()
+            .find(|subdir| *subdir.get_subdir_type() == dir_type)
     }
 }
 
-impl Subdir {
-    pub fn new(subdir_path_string: String, subdir_type: DirType) -> Result<Self> {
-        let path = Path::new(&subdir_path_string);
-        let path = if path.exists() {
-            path.into()
-        } else {
-            return Err(format!("could not locate {subdir_path_string}").into());
-        };
-
-        Ok(Self { path, subdir_type })
-    }
+fn follow_symlinks() -> bool {
+    std::env::var("FOLLOW_SYMLINKS")
+        .map(|value| value == "true")
+        .unwrap_or(false)
+}
 
-    pub fn get_path(&self) -> &Path {
-        &self.path
-    }
+fn dir_type_paths(project_root: &str) -> Result<Vec<(DirType, PathBuf)>> {
+    DirType::ALL
+        .into_iter()
+        .map(|dir_type| {
+            let base_path = std::env::var(dir_type.path_var())?;
+            Ok((
+                dir_type,
+                PathBuf::from(format!("{project_root}{base_path}")),
+            ))
+        })
+        .collect()
+}
 
-    pub fn get_subdir_type(&self) -> &DirType {
-        &self.subdir_type
-    }
+fn get_subdir_path_string(project_root: &str, dir_type: DirType, feature: &str) -> Result<String> {
+    let base_path = std::env::var(dir_type.path_var())?;
+    Ok(format!("{project_root}{base_path}{feature}"))
 }
 
-pub fn get_project_root(current_dir: &str) -> Result<String> {
-    let command_output = match Command::new("git")
-        .current_dir(current_dir)
-        .arg("rev-parse")
-        .arg("--show-toplevel")
-        .output()
-    {
-        Ok(output) => output,
-        Err(_) => return Err("could not run command 'git rev-parse --show-toplevel'".into()),
-    };
+impl<'a> RuleContext<'a> {
+    pub fn new(project: &'a Project, subdir: &'a Subdir) -> Self {
+        Self {
+            project,
+            subdir,
+            rule_config: None,
+        }
+    }
 
-    let stderr = str::from_utf8(&command_output.stderr)?;
-    if !stderr.is_empty() {
-        return Err(stderr.into());
+    pub fn with_rule_config(mut self, rule_config: Option<&'a RuleConfig>) -> Self {
+        self.rule_config = rule_config;
+        self
     }
 
-    let stdout = str::from_utf8(&command_output.stdout)?.trim_end();
-    if stdout.is_empty() {
-        return Err("'git rev-parse --show-toplevel' output is empty".into());
+    pub fn get_project(&self) -> &Project {
+        self.project
     }
-    let repo_name = std::env::var("REPOSITORY_NAME").unwrap_or(stdout.to_owned());
-    if !cfg!(debug_assertions) && !stdout.ends_with(&repo_name) {
-        return Err("not in the correct repository".into());
+
+    pub fn get_subdir(&self) -> &Subdir {
+        self.subdir
     }
 
-    let repository_path = std::env::var("REPOSITORY_PATH")?;
-    let project_root = if cfg!(debug_assertions) {
-        &repository_path
-    } else {
-        stdout
-    };
+    /// A rule specific option from `lint_apptester.toml`.
+    pub fn get_option(&self, key: &str) -> Option<&toml::Value> {
+        self.rule_config?.get_option(key)
+    }
 
-    Ok(project_root.to_owned())
+    /// A string option from `lint_apptester.toml`, falling back to the
+    /// environment variable `env_var`.
+    pub fn get_string_option(&self, key: &str, env_var: &str) -> Option<String> {
+        match self.get_option(key).and_then(|value| value.as_str()) {
+            Some(value) => Some(String::from(value)),
+            None => std::env::var(env_var).ok(),
+        }
+    }
 }
 
-pub fn print_results(rules: Vec<&Rule>, rule_status_map: HashMap<&str, bool>) {
-    for &rule in &rules {
-        println!(
-            "  - {}: {}",
-            rule.get_name(),
-            if *rule_status_map.get(rule.get_name()).unwrap() {
-                "PASS".green()
-            } else {
-                "FAIL".red()
-            }
-        );
+impl Rules {
+    pub fn init() -> Self {
+        Self {
+            rules: Vec::new(),
+            config: LintConfig::default(),
+            warnings: Vec::new(),
+        }
     }
-}
 
-pub fn process_subdir(subdir: &Subdir, rules: &Rules) -> Result<()> {
-    let rules: Vec<&Rule> = rules
-        .get_rules()
-        .iter()
-        .filter(|&rule| rule.get_dir_types().contains(subdir.get_subdir_type()))
-        .collect();
-    println!(
-        "{:?} ({}):",
-        subdir.get_subdir_type(),
-        subdir.get_path().to_str().unwrap()
-    );
-    if rules.is_empty() {
-        println!("  # No rules for this directory");
-        return Ok(());
+    pub fn add_rule(&mut self, rule: impl LintRule + 'static) {
+        self.rules.push(Box::new(rule));
     }
 
-    // TODO: Map rule to Vec<u16> (line numbers with problems)
-    let mut rule_status_map: HashMap<&str, bool> = HashMap::new();
-    for &rule in &rules {
-        rule_status_map.insert(rule.get_name(), true);
+    pub fn get_rules(&self) -> &Vec<Box<dyn LintRule>> {
+        &self.rules
     }
 
-    let dir = fs::read_dir(subdir.get_path()).unwrap();
-    for entry in dir {
-        let entry = entry?;
-        if !["feature", "java", "js"].contains(
-            &entry
-                .path()
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default(),
-        ) {
-            continue;
+    /// Applies the project's configuration, adding its custom rules or
+    /// replacing the rules they share an id with.
+    pub fn configure(&mut self, config: LintConfig) -> Result<()> {
+        let mut custom_rule_ids = HashSet::new();
+        for custom_rule in config.get_custom_rules() {
+            if !custom_rule_ids.insert(custom_rule.get_id()) {
+                return Err(
+                    format!("custom rule '{}' is declared twice", custom_rule.get_id()).into(),
+                );
+            }
+            let rule = Box::new(CustomRule::new(custom_rule)?);
+            match self
+                .rules
+                .iter()
+                .position(|existing| existing.id() == rule.id())
+            {
+                Some(index) => {
+                    if self.rules[index].has_fixes() {
+                        self.warnings.push(format!(
+                            "custom rule '{}' replaces a rule with fixes, so what it reports can't be fixed",
+                            rule.id()
+                        ));
+                    }
+                    self.rules[index] = rule;
+                }
+                // Before unused-suppression, which must come last
+                None => {
+                    let index = self
+                        .rules
+                        .iter()
+                        .position(|existing| existing.id() == UnusedSuppression.id())
+                        .unwrap_or(self.rules.len());
+                    self.rules.insert(index, rule);
+                }
+            }
         }
 
-        for &rule in &rules {
-            let file = File::open(entry.path())?; // Inefficient: Pass buffer.by_ref() to closure - figure out
-            if rule.get_dir_types().contains(subdir.get_subdir_type()) && !(rule.get_rule())(&file)
-            {
-                rule_status_map.insert(rule.get_name(), false);
+        for rule_id in config.get_rule_ids() {
+            let rule = match self.rules.iter().find(|rule| rule.id() == rule_id) {
+                Some(rule) => rule,
+                None => return Err(format!("unknown rule '{rule_id}' in configuration").into()),
+            };
+            let rule_config = config.get_rule_config(rule_id).unwrap();
+            for key in rule_config.get_option_keys() {
+                if !rule.options().contains(&key.as_str()) {
+                    let known: Vec<&str> = ["enabled", "severity", "dir_types"]
+                        .iter()
+                        .chain(rule.options())
+                        .copied()
+                        .collect();
+                    return Err(format!(
+                        "unknown option '{key}' for rule '{rule_id}', expected one of {}",
+                        known.join(", ")
+                    )
+                    .into());
+                }
             }
+            rule.check_options(rule_config)
+                .map_err(|message| format!("invalid options for rule '{rule_id}': {message}"))?;
         }
+        self.config = config;
+
+        Ok(())
     }
 
-    print_results(rules, rule_status_map);
-    Ok(())
-}
+    /// Checks every enabled rule against the layout of the projects it will lint.
+    pub fn check_projects(&self, projects: &[Project]) -> Result<()> {
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| self.is_enabled(rule.as_ref()))
+        {
+            for project in projects {
+                rule.check_project(project, self.get_rule_config(rule.as_ref()))
+                    .map_err(|message| format!("rule '{}' can't be used: {message}", rule.id()))?;
+            }
+        }
 
-pub mod rules {
-    use std::{
-        fs::File,
-        io::{BufRead, BufReader},
-    };
+        Ok(())
+    }
 
-    use crate::{DirType, Rule, Rules};
+    /// Problems found by [`Rules::configure`] that don't stop the rules running.
+    pub fn get_warnings(&self) -> &[String] {
+        &self.warnings
+    }
 
-    pub fn get_rules() -> Rules {
-        let mut rules = Rules::init();
-        rules.add_rule(get_log_instead_of_sout());
-        rules.add_rule(get_no_assert_calls());
-        rules.add_rule(get_no_locator_calls());
-        rules.add_rule(get_platform_locator_methods());
+    /// The custom rules declared in the configuration.
+    pub fn get_custom_rules(&self) -> &[CustomRuleConfig] {
+        self.config.get_custom_rules()
+    }
 
-        rules
+    pub fn get_rule_config(&self, rule: &dyn LintRule) -> Option<&RuleConfig> {
+        self.config.get_rule_config(rule.id())
     }
 
-    fn get_log_instead_of_sout() -> Rule {
-        Rule::new(
-            "Log instead of sout",
-            |file: &File| {
-                let buffered_reader = BufReader::new(file);
-                buffered_reader
-                    .lines()
-                    .map(|line| {
-                        let line = line.unwrap();
-                        return line.trim().to_owned();
-                    })
-                    .skip_while(|line| !line.starts_with("public class"))
-                    .filter(|line| !line.starts_with("//"))
-                    .all(|line| !line.starts_with("System.out.print"))
+    pub fn is_enabled(&self, rule: &dyn LintRule) -> bool {
+        rule.is_enabled_by_default()
+            && self
+                .get_rule_config(rule)
+                .is_none_or(|rule_config| rule_config.is_enabled())
+    }
+
+    pub fn get_severity(&self, rule: &dyn LintRule) -> Severity {
+        self.get_rule_config(rule)
+            .and_then(|rule_config| rule_config.get_severity())
+            .unwrap_or_else(|| rule.severity())
+    }
+
+    pub fn get_dir_types<'a>(&'a self, rule: &'a dyn LintRule) -> &'a [DirType] {
+        self.get_rule_config(rule)
+            .and_then(|rule_config| rule_config.get_dir_types())
+            .unwrap_or_else(|| rule.dir_types())
+    }
+}
+
+impl Report {
+    pub fn new(project_root: &str, all_features: bool) -> Self {
+        Self {
+            project_root: String::from(project_root),
+            all_features,
+            features: Vec::new(),
+        }
+    }
+
+    pub fn add_feature(&mut self, feature: FeatureReport) {
+        self.features.push(feature);
+    }
+
+    pub fn get_project_root(&self) -> &str {
+        &self.project_root
+    }
+
+    pub fn get_all_features(&self) -> bool {
+        self.all_features
+    }
+
+    pub fn get_features(&self) -> &Vec<FeatureReport> {
+        &self.features
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &RuleReport> {
+        self.features
+            .iter()
+            .flat_map(|feature| feature.get_subdirs())
+            .flat_map(|subdir| subdir.get_rules())
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.rules()
+            .flat_map(|rule| rule.get_diagnostics().iter().cloned())
+            .collect()
+    }
+
+    /// Keeps only the diagnostics for which `keep` returns true.
+    pub fn retain_diagnostics(&mut self, mut keep: impl FnMut(&Diagnostic) -> bool) {
+        for feature in &mut self.features {
+            for subdir in &mut feature.subdirs {
+                for rule in &mut subdir.rules {
+                    rule.diagnostics.retain(&mut keep);
+                }
+            }
+        }
+    }
+}
+
+impl FeatureReport {
+    pub fn new(feature: &str) -> Self {
+        Self {
+            feature: String::from(feature),
+            subdirs: Vec::new(),
+        }
+    }
+
+    pub fn add_subdir(&mut self, subdir: SubdirReport) {
+        self.subdirs.push(subdir);
+    }
+
+    pub fn get_feature(&self) -> &str {
+        &self.feature
+    }
+
+    pub fn get_subdirs(&self) -> &Vec<SubdirReport> {
+        &self.subdirs
+    }
+
+    pub fn problem_count(&self) -> usize {
+        self.subdirs
+            .iter()
+            .flat_map(|subdir| subdir.get_rules())
+            .map(|rule| rule.get_diagnostics().len())
+            .sum()
+    }
+}
+
+impl SubdirReport {
+    pub fn get_dir_type(&self) -> DirType {
+        self.dir_type
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_rules(&self) -> &Vec<RuleReport> {
+        &self.rules
+    }
+}
+
+impl RuleReport {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn get_diagnostics(&self) -> &Vec<Diagnostic> {
+        &self.diagnostics
+    }
+}
+
+impl SourceFile {
+    pub fn read(path: &Path, dir_type: DirType) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Ok(Self::from_contents(path, dir_type, &contents))
+    }
+
+    pub fn from_contents(path: &Path, dir_type: DirType, contents: &str) -> Self {
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_str()
+            .unwrap_or_default();
+        let is_code = ["java", "js"].contains(&extension);
+
+        Self {
+            path: path.to_path_buf(),
+            dir_type,
+            lines: contents.lines().map(String::from).collect(),
+            line_ending: line_ending(contents),
+            tokens: if is_code {
+                java::tokenize(contents)
+            } else {
+                Vec::new()
             },
-            vec![DirType::Interactions, DirType::Pages, DirType::Steps],
-        )
+            gherkin: (extension == "feature").then(|| GherkinDocument::parse(contents)),
+        }
     }
 
-    fn get_no_assert_calls() -> Rule {
-        Rule::new(
-            "No assert calls",
-            |file: &File| {
-                let buffered_reader = BufReader::new(file);
-                buffered_reader
-                    .lines()
-                    .map(|line| {
-                        let line = line.unwrap();
-                        return line.trim().to_owned();
-                    })
-                    .skip_while(|line| !line.starts_with("public class"))
-                    .filter(|line| !line.starts_with("//"))
-                    .all(|line| !line.contains("assert"))
+    /// A file with its lines but no Gherkin document, and only tokens if it may
+    /// have suppression comments, for when rules don't need to check it again.
+    fn lines_only(path: &Path, dir_type: DirType, contents: &str) -> Self {
+        let is_code = path
+            .extension()
+            .is_some_and(|extension| extension == "java" || extension == "js");
+
+        Self {
+            path: path.to_path_buf(),
+            dir_type,
+            lines: contents.lines().map(String::from).collect(),
+            line_ending: line_ending(contents),
+            tokens: match is_code && contents.contains(suppressions::DIRECTIVE_PREFIX) {
+                true => java::tokenize(contents),
+                false => Vec::new(),
             },
-            vec![DirType::Steps],
-        )
+            gherkin: None,
+        }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_dir_type(&self) -> DirType {
+        self.dir_type
+    }
+
+    /// What lines end with, for text inserted by fixes.
+    pub fn get_line_ending(&self) -> &'static str {
+        self.line_ending
+    }
+
+    /// Every line of the file, numbered from 1.
+    pub fn lines(&self) -> impl Iterator<Item = SourceLine<'_>> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(index, text)| SourceLine::new(index + 1, text))
+    }
+
+    /// Every token of a Java or JavaScript file, including comments.
+    pub fn get_tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Tokens that are not comments.
+    pub fn code_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.tokens
+            .iter()
+            .filter(|token| !token.get_kind().is_comment())
+    }
+
+    /// Code tokens from the class declaration onwards, i.e. without the
+    /// package and imports.
+    pub fn class_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.code_tokens().skip(self.class_declaration())
+    }
+
+    /// Code tokens before the class declaration, i.e. the package and imports.
+    pub fn header_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.code_tokens().take(self.class_declaration())
+    }
+
+    /// The index among the code tokens of the keyword declaring the class,
+    /// interface, enum, record or annotation type. A class literal, e.g.
+    /// `@RunWith(Cucumber.class)`, isn't a declaration.
+    fn class_declaration(&self) -> usize {
+        let tokens: Vec<&Token> = self.code_tokens().collect();
+        (0..tokens.len())
+            .find(|&index| {
+                let follows_dot = index > 0 && tokens[index - 1].is_punctuation(".");
+                is_class_declaration(tokens[index], tokens.get(index + 1).copied()) && !follows_dot
+            })
+            .unwrap_or(usize::MAX)
+    }
+
+    /// The parsed document of a `.feature` file.
+    pub fn get_gherkin(&self) -> Option<&GherkinDocument> {
+        self.gherkin.as_ref()
+    }
+
+    pub fn get_imports(&self) -> Vec<Import> {
+        java::parse_imports(self.code_tokens())
+    }
+
+    /// Builds a diagnostic pointing at `column` (0-based) of line `line`, showing
+    /// that line as the offending text.
+    pub fn diagnostic(
+        &self,
+        rule_id: &str,
+        line: usize,
+        column: usize,
+        message: &str,
+    ) -> Diagnostic {
+        let text = self.lines.get(line - 1).map_or("", |text| text.trim());
+        Diagnostic::new(rule_id, &self.path, line, column + 1, text, message)
+    }
+
+    pub fn token_diagnostic(&self, rule_id: &str, token: &Token, message: &str) -> Diagnostic {
+        self.diagnostic(rule_id, token.get_line(), token.get_column(), message)
+    }
+}
+
+fn line_ending(contents: &str) -> &'static str {
+    match contents.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    }
+}
+
+fn is_class_declaration(token: &Token, next: Option<&Token>) -> bool {
+    match token.get_kind() {
+        TokenKind::Keyword => ["class", "interface", "enum"].contains(&token.get_text()),
+        // `record` is only a keyword where it declares a record, e.g. `record Point(`
+        TokenKind::Identifier => {
+            token.get_text() == "record"
+                && next.is_some_and(|next| next.get_kind() == TokenKind::Identifier)
+        }
+        _ => false,
+    }
+}
+
+impl<'a> SourceLine<'a> {
+    pub fn new(number: usize, text: &'a str) -> Self {
+        Self { number, text }
     }
 
-    fn get_no_locator_calls() -> Rule {
-        Rule::new(
-            "No locator calls",
-            |file: &File| {
-                let locator_class_path = match std::env::var("LOCATOR_CLASS_PATH") {
-                    Ok(path) => path,
-                    Err(_) => {
-                        eprintln!("Could not find variable LOCATOR_CLASS_PATH");
-                        return false; // Will always fail
+    pub fn get_number(&self) -> usize {
+        self.number
+    }
+
+    pub fn get_text(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn trimmed(&self) -> &'a str {
+        self.text.trim()
+    }
+}
+
+impl Subdir {
+    pub fn new(subdir_path_string: String, subdir_type: DirType) -> Result<Self> {
+        let path = Path::new(&subdir_path_string);
+        let path = if path.exists() {
+            path.into()
+        } else {
+            return Err(format!("could not locate {subdir_path_string}").into());
+        };
+
+        Ok(Self { path, subdir_type })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_subdir_type(&self) -> &DirType {
+        &self.subdir_type
+    }
+
+    /// Lintable files anywhere under the subdir, sorted by path so output is stable.
+    pub fn get_files(&self, follow_symlinks: bool) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut visited_dirs = HashSet::new();
+        collect_files(&self.path, follow_symlinks, &mut visited_dirs, &mut files)?;
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+fn collect_files(
+    dir: &Path,
+    follow_symlinks: bool,
+    visited_dirs: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    // Guards against symlink cycles when following links
+    if !visited_dirs.insert(fs::canonicalize(dir)?) {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_symlink() && !follow_symlinks {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(&path, follow_symlinks, visited_dirs, files)?;
+        } else if path.is_file()
+            && LINTABLE_EXTENSIONS.contains(
+                &path
+                    .extension()
+                    .unwrap_or_default()
+                    .to_str()
+                    .unwrap_or_default(),
+            )
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn get_project_root(current_dir: &str) -> Result<String> {
+    let command_output = match Command::new("git")
+        .current_dir(current_dir)
+        .arg("rev-parse")
+        .arg("--show-toplevel")
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Err("could not run command 'git rev-parse --show-toplevel'".into()),
+    };
+
+    let stderr = str::from_utf8(&command_output.stderr)?;
+    if !stderr.is_empty() {
+        return Err(stderr.into());
+    }
+
+    let stdout = str::from_utf8(&command_output.stdout)?.trim_end();
+    if stdout.is_empty() {
+        return Err("'git rev-parse --show-toplevel' output is empty".into());
+    }
+    let repo_name = std::env::var("REPOSITORY_NAME").unwrap_or(stdout.to_owned());
+    if !cfg!(debug_assertions) && !stdout.ends_with(&repo_name) {
+        return Err("not in the correct repository".into());
+    }
+
+    let repository_path = std::env::var("REPOSITORY_PATH")?;
+    let project_root = if cfg!(debug_assertions) {
+        &repository_path
+    } else {
+        stdout
+    };
+
+    Ok(project_root.to_owned())
+}
+
+/// Lints every project, in parallel, returning their reports in order.
+pub fn process_projects(
+    projects: &[Project],
+    rules: &Rules,
+    changes: Option<&ChangeSet>,
+    cache: Option<&Cache>,
+) -> Result<Vec<FeatureReport>> {
+    // Projects share the step index, so it's built once before any of them
+    if let Some(project) = projects.first() {
+        project.prepare_step_index(rules);
+    }
+    let feature_reports = projects
+        .par_iter()
+        .map(|project| {
+            process_project(project, rules, changes, cache).map_err(|err| err.to_string())
+        })
+        .collect::<std::result::Result<Vec<FeatureReport>, String>>()?;
+
+    Ok(feature_reports)
+}
+
+/// Lints every subdir of `project`, in parallel.
+pub fn process_project(
+    project: &Project,
+    rules: &Rules,
+    changes: Option<&ChangeSet>,
+    cache: Option<&Cache>,
+) -> Result<FeatureReport> {
+    project.prepare_step_index(rules);
+    let subdir_reports = project
+        .subdirs
+        .par_iter()
+        .map(|subdir| {
+            process_subdir(project, subdir, rules, changes, cache).map_err(|err| err.to_string())
+        })
+        .collect::<std::result::Result<Vec<SubdirReport>, String>>()?;
+
+    Ok(FeatureReport {
+        feature: project.get_feature_being_tested().to_owned(),
+        subdirs: subdir_reports,
+    })
+}
+
+/// Lints every file in `subdir` and collects the diagnostics of each rule. With
+/// `changes`, only changed files are linted and only their diagnostics kept.
+/// With `cache`, files that haven't changed since they were cached aren't
+/// checked again by rules that only look at one file.
+pub fn process_subdir(
+    project: &Project,
+    subdir: &Subdir,
+    rules: &Rules,
+    changes: Option<&ChangeSet>,
+    cache: Option<&Cache>,
+) -> Result<SubdirReport> {
+    let enabled_rules = enabled_rules(rules, subdir);
+
+    let mut rule_reports: Vec<RuleReport> = enabled_rules
+        .iter()
+        .map(|&rule| RuleReport {
+            id: String::from(rule.id()),
+            description: String::from(rule.description()),
+            severity: rules.get_severity(rule),
+            diagnostics: Vec::new(),
+        })
+        .collect();
+
+    if !enabled_rules.is_empty() {
+        let paths: Vec<PathBuf> = subdir
+            .get_files(project.get_follow_symlinks())?
+            .into_iter()
+            .filter(|path| changes.is_none_or(|changes| changes.includes_file(path)))
+            .collect();
+
+        // Files the step index has read and parsed aren't read again
+        let step_index = (StepIndex::DIR_TYPES.contains(subdir.get_subdir_type())
+            && enabled_rules.iter().any(|rule| rule.is_cross_file()))
+        .then(|| project.get_step_index());
+
+        // Files are linted in parallel and their diagnostics merged in path order
+        let file_diagnostics: Vec<Vec<Vec<Diagnostic>>> = paths
+            .par_iter()
+            .map(|path| {
+                let staged = changes.and_then(|changes| changes.get_staged(path));
+                let indexed = step_index.as_ref().and_then(|index| index.get_file(path));
+                let read;
+                let contents = match (staged, indexed) {
+                    (Some(staged), _) => Contents::Text(staged),
+                    (None, Some((contents, file))) => Contents::Parsed(contents, file),
+                    (None, None) => {
+                        read = fs::read_to_string(path)
+                            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+                        Contents::Text(&read)
                     }
                 };
+                Ok(lint_file(
+                    project,
+                    subdir,
+                    rules,
+                    &enabled_rules,
+                    path,
+                    contents,
+                    cache,
+                ))
+            })
+            .collect::<std::result::Result<_, String>>()?;
+
+        for diagnostics_by_rule in file_diagnostics {
+            for (rule_report, mut diagnostics) in rule_reports.iter_mut().zip(diagnostics_by_rule) {
+                diagnostics.retain(|diagnostic| {
+                    changes.is_none_or(|changes| changes.includes(diagnostic))
+                });
+                for diagnostic in &mut diagnostics {
+                    diagnostic.set_severity(rule_report.severity);
+                }
+                rule_report.diagnostics.extend(diagnostics);
+            }
+        }
+    }
 
-                let buffered_reader = BufReader::new(file);
-                buffered_reader
-                    .lines()
-                    .map(|line| {
-                        let line = line.unwrap();
-                        return line.trim().to_owned();
-                    })
-                    .take_while(|line| !line.starts_with("public class"))
-                    .filter(|line| !line.starts_with("//"))
-                    .all(|line| !line.starts_with(&locator_class_path))
-            },
-            vec![DirType::Steps, DirType::Interactions],
-        )
+    Ok(SubdirReport {
+        dir_type: *subdir.get_subdir_type(),
+        path: subdir.get_path().to_path_buf(),
+        rules: rule_reports,
+    })
+}
+
+/// Lints `contents` as the file at `path` in `subdir`, e.g. an unsaved editor
+/// buffer, and returns its diagnostics in rule order.
+pub fn lint_source(
+    project: &Project,
+    subdir: &Subdir,
+    rules: &Rules,
+    path: &Path,
+    contents: &str,
+) -> Vec<Diagnostic> {
+    let enabled_rules = enabled_rules(rules, subdir);
+    lint_file(
+        project,
+        subdir,
+        rules,
+        &enabled_rules,
+        path,
+        Contents::Text(contents),
+        None,
+    )
+    .into_iter()
+    .zip(&enabled_rules)
+    .flat_map(|(mut diagnostics, &rule)| {
+        for diagnostic in &mut diagnostics {
+            diagnostic.set_severity(rules.get_severity(rule));
+        }
+        diagnostics
+    })
+    .collect()
+}
+
+fn enabled_rules<'a>(rules: &'a Rules, subdir: &Subdir) -> Vec<&'a dyn LintRule> {
+    rules
+        .get_rules()
+        .iter()
+        .map(|rule| rule.as_ref())
+        .filter(|&rule| rules.is_enabled(rule))
+        .filter(|&rule| rules.get_dir_types(rule).contains(subdir.get_subdir_type()))
+        .collect()
+}
+
+/// A file's contents to lint.
+enum Contents<'a> {
+    /// Read but not yet parsed
+    Text(&'a str),
+    /// Already parsed, by the step index
+    Parsed(&'a str, &'a SourceFile),
+}
+
+impl Contents<'_> {
+    fn get_text(&self) -> &str {
+        match self {
+            Contents::Text(text) | Contents::Parsed(text, _) => text,
+        }
     }
+}
 
-    fn get_platform_locator_methods() -> Rule {
-        Rule::new(
-            "Use platform Locator methods",
-            |file: &File| {
-                let buffered_reader = BufReader::new(file);
-                let matches = [
-                    "Platform",
-                    "Children",
-                    "ClassName",
-                    "ByText",
-                    "TidIsPresent",
-                ];
-                buffered_reader
+/// Checks one file with every rule in `enabled_rules` and returns the
+/// diagnostics left after suppressions, one list per rule. The file is parsed
+/// once and shared by every rule.
+fn lint_file(
+    project: &Project,
+    subdir: &Subdir,
+    rules: &Rules,
+    enabled_rules: &[&dyn LintRule],
+    path: &Path,
+    contents: Contents,
+    cache: Option<&Cache>,
+) -> Vec<Vec<Diagnostic>> {
+    let text = contents.get_text();
+    let cached = cache
+        .and_then(|cache| cache.get(path, text))
+        .filter(|cached| {
+            enabled_rules
+                .iter()
+                .filter(|rule| !rule.is_cross_file())
+                .all(|rule| cached.contains_key(rule.id()))
+        });
+    // Suppressions only need the lines and comments, so cached files are only
+    // parsed if a cross-file rule still has to check them
+    let parsed;
+    let file = match contents {
+        Contents::Parsed(_, file) => file,
+        Contents::Text(text) => {
+            parsed = if cached.is_none() || enabled_rules.iter().any(|rule| rule.is_cross_file()) {
+                SourceFile::from_contents(path, *subdir.get_subdir_type(), text)
+            } else {
+                SourceFile::lines_only(path, *subdir.get_subdir_type(), text)
+            };
+            &parsed
+        }
+    };
+    let mut suppressions = Suppressions::parse(file);
+    let mut to_cache: HashMap<String, Vec<Diagnostic>> = HashMap::new();
+    let mut diagnostics_by_rule: Vec<Vec<Diagnostic>> = enabled_rules
+        .iter()
+        .map(|&rule| {
+            let mut diagnostics = match &cached {
+                Some(cached) if !rule.is_cross_file() => cached[rule.id()].clone(),
+                _ => {
+                    let context = RuleContext::new(project, subdir)
+                        .with_rule_config(rules.get_rule_config(rule));
+                    rule.check(file, &context)
+                }
+            };
+            if !rule.is_cross_file() {
+                to_cache.insert(String::from(rule.id()), diagnostics.clone());
+            }
+            diagnostics.retain(|diagnostic| !suppressions.suppresses(diagnostic));
+            diagnostics
+        })
+        .collect();
+    if let (Some(cache), None) = (cache, &cached) {
+        cache.insert(path, text, to_cache);
+    }
+
+    if let Some(index) = enabled_rules
+        .iter()
+        .position(|rule| rule.id() == UnusedSuppression.id())
+    {
+        diagnostics_by_rule[index] = suppressions.unused(
+            enabled_rules[index].id(),
+            file,
+            |rule_id| enabled_rules.iter().any(|rule| rule.id() == rule_id),
+            |rule_id| rules.get_rules().iter().any(|rule| rule.id() == rule_id),
+        );
+    }
+    diagnostics_by_rule
+}
+
+pub mod rules {
+    use std::collections::HashSet;
+
+    use crate::{
+        fixes::{Edit, Fix},
+        java::{Token, TokenKind},
+        Diagnostic, DirType, LintRule, RuleContext, Rules, Severity, SourceFile,
+    };
+
+    const DEFAULT_LOGGER_CALL: &str = "LOGGER.info";
+    const DEFAULT_LOGGER_FIELD: &str =
+        "private static final Logger LOGGER = LoggerFactory.getLogger({class}.class);";
+    const DEFAULT_LOGGER_IMPORTS: [&str; 2] = ["org.slf4j.Logger", "org.slf4j.LoggerFactory"];
+
+    mod custom;
+    mod features;
+    mod layering;
+    mod step_definitions;
+
+    pub use custom::{built_in_rules, CustomRule};
+    pub use features::{
+        FeatureNames, FeatureWhitespace, GivenWhenThenOrder, MaxScenarioSteps,
+        NoDuplicateScenarioNames, NoEmptyScenarios,
+    };
+    pub use layering::Layering;
+    pub use step_definitions::{UndefinedSteps, UnusedStepDefinitions};
+
+    pub fn get_rules() -> Rules {
+        let mut rules = Rules::init();
+        rules.add_rule(LogInsteadOfSout);
+        for rule in built_in_rules() {
+            rules.add_rule(rule);
+        }
+        rules.add_rule(NoLocatorCalls);
+        rules.add_rule(PlatformLocatorMethods);
+        rules.add_rule(Layering);
+        rules.add_rule(FeatureNames);
+        rules.add_rule(NoEmptyScenarios);
+        rules.add_rule(NoDuplicateScenarioNames);
+        rules.add_rule(GivenWhenThenOrder);
+        rules.add_rule(MaxScenarioSteps);
+        rules.add_rule(FeatureWhitespace);
+        rules.add_rule(UndefinedSteps);
+        rules.add_rule(UnusedStepDefinitions);
+        rules.add_rule(UnusedSuppression);
+
+        rules
+    }
+
+    pub struct LogInsteadOfSout;
+
+    impl LintRule for LogInsteadOfSout {
+        fn id(&self) -> &str {
+            "log-instead-of-sout"
+        }
+
+        fn description(&self) -> &str {
+            "Log instead of sout"
+        }
+
+        fn dir_types(&self) -> &[DirType] {
+            &[DirType::Interactions, DirType::Pages, DirType::Steps]
+        }
+
+        fn severity(&self) -> Severity {
+            Severity::Warning
+        }
+
+        fn options(&self) -> &[&str] {
+            &["logger_call", "logger_field", "logger_imports"]
+        }
+
+        fn has_fixes(&self) -> bool {
+            true
+        }
+
+        fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic> {
+            let tokens: Vec<&Token> = file.class_tokens().collect();
+            let logger_call = context
+                .get_string_option("logger_call", "LOGGER_CALL")
+                .unwrap_or(String::from(DEFAULT_LOGGER_CALL));
+            let logger_edits = logger_edits(file, context, &logger_call);
+            tokens
+                .windows(5)
+                .enumerate()
+                .filter(|(_, window)| {
+                    window[0].is(TokenKind::Identifier, "System")
+                        && window[1].is_punctuation(".")
+                        && window[2].is(TokenKind::Identifier, "out")
+                        && window[3].is_punctuation(".")
+                        && window[4].get_text().starts_with("print")
+                })
+                .map(|(index, window)| {
+                    let diagnostic = file.token_diagnostic(
+                        self.id(),
+                        window[0],
+                        "use a logger instead of System.out.print",
+                    );
+                    // printf needs its format applying and println() has nothing to log
+                    let is_fixable = ["print", "println"].contains(&window[4].get_text())
+                        && tokens
+                            .get(index + 5)
+                            .is_some_and(|token| token.is_punctuation("("))
+                        && tokens
+                            .get(index + 6)
+                            .is_some_and(|token| !token.is_punctuation(")"));
+                    match &logger_edits {
+                        Some(logger_edits) if is_fixable => {
+                            let mut edits = vec![Edit::replace(
+                                window[0].get_line(),
+                                window[0].get_column(),
+                                window[4].get_line(),
+                                window[4].get_column() + window[4].get_text().len(),
+                                &logger_call,
+                            )];
+                            // Loggers take a message, so anything else is formatted into one
+                            let argument = tokens[index + 6];
+                            if !is_string_argument(&tokens[index + 6..]) {
+                                edits.push(Edit::insert(
+                                    argument.get_line(),
+                                    argument.get_column(),
+                                    "\"{}\", ",
+                                ));
+                            }
+                            edits.extend(logger_edits.iter().cloned());
+                            diagnostic.with_fix(Fix::new("log with the configured logger", edits))
+                        }
+                        _ => diagnostic,
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// Whether the argument starting at `tokens` is a string, i.e. a string
+    /// literal on its own or concatenated with something.
+    fn is_string_argument(tokens: &[&Token]) -> bool {
+        tokens[0].get_kind() == TokenKind::StringLiteral
+            && tokens
+                .get(1)
+                .is_some_and(|token| token.is_punctuation(")") || token.is_punctuation("+"))
+    }
+
+    /// Edits adding the logger's imports and field to `file` when it doesn't have
+    /// them, or `None` if there's no class to add the field to.
+    fn logger_edits(
+        file: &SourceFile,
+        context: &RuleContext,
+        logger_call: &str,
+    ) -> Option<Vec<Edit>> {
+        let logger_field = context
+            .get_string_option("logger_field", "LOGGER_FIELD")
+            .unwrap_or(String::from(DEFAULT_LOGGER_FIELD));
+        let logger_imports: Vec<String> = match context.get_option("logger_imports") {
+            Some(imports) => imports
+                .as_array()?
+                .iter()
+                .filter_map(|import| import.as_str().map(String::from))
+                .collect(),
+            None => DEFAULT_LOGGER_IMPORTS.map(String::from).to_vec(),
+        };
+
+        let class_tokens: Vec<&Token> = file.class_tokens().collect();
+        let class_name = class_tokens.get(1)?.get_text();
+        let open_brace = class_tokens
+            .iter()
+            .find(|token| token.is_punctuation("{"))?;
+        let mut edits = Vec::new();
+
+        let imports = file.get_imports();
+        let missing_imports: Vec<&String> = logger_imports
+            .iter()
+            .filter(|logger_import| {
+                !imports.iter().any(|import| {
+                    let path = import.get_path();
+                    path == logger_import.as_str()
+                        || path.strip_suffix(".*").is_some_and(|package| {
+                            logger_import.rsplit_once('.').map(|(p, _)| p) == Some(package)
+                        })
+                })
+            })
+            .collect();
+        let eol = file.get_line_ending();
+        if !missing_imports.is_empty() {
+            let import_lines: Vec<String> = missing_imports
+                .iter()
+                .map(|import| format!("import {import};"))
+                .collect();
+            let import_lines = import_lines.join(eol);
+            let package = file
+                .code_tokens()
+                .find(|token| token.is(TokenKind::Keyword, "package"));
+            edits.push(match (imports.last(), package) {
+                (Some(last_import), _) => Edit::insert(
+                    last_import.get_line(),
+                    line_length(file, last_import.get_line()),
+                    &format!("{eol}{import_lines}"),
+                ),
+                (None, Some(package)) => Edit::insert(
+                    package.get_line(),
+                    line_length(file, package.get_line()),
+                    &format!("{eol}{eol}{import_lines}"),
+                ),
+                (None, None) => Edit::insert(1, 0, &format!("{import_lines}{eol}{eol}")),
+            });
+        }
+
+        // e.g. `LOGGER` from `LOGGER.info`; a call without a receiver needs no field
+        if let Some((field_name, _)) = logger_call.rsplit_once('.') {
+            let has_field = class_tokens.windows(2).any(|window| {
+                (window[0].get_kind() == TokenKind::Identifier || window[0].is_punctuation(">"))
+                    && window[1].is(TokenKind::Identifier, field_name)
+            });
+            if !has_field {
+                let indent = file
                     .lines()
+                    .skip(open_brace.get_line())
+                    .find(|line| !line.get_text().trim().is_empty())
                     .map(|line| {
-                        let line = line.unwrap();
-                        return line.trim().to_owned();
+                        let text = line.get_text();
+                        String::from(&text[..text.len() - text.trim_start().len()])
                     })
-                    .skip_while(|line| !line.starts_with("public class"))
-                    .filter(|line| !line.starts_with("//"))
-                    .filter(|line| line.contains("Locator."))
-                    .all(|line| matches.iter().any(|&m| line.contains(m)))
-            },
-            vec![DirType::Pages],
-        )
+                    .filter(|indent| !indent.is_empty())
+                    .unwrap_or(String::from("    "));
+                edits.push(Edit::insert(
+                    open_brace.get_line(),
+                    open_brace.get_column() + 1,
+                    &format!(
+                        "{eol}{indent}{}",
+                        logger_field.replace("{class}", class_name)
+                    ),
+                ));
+            }
+        }
+
+        Some(edits)
+    }
+
+    fn line_length(file: &SourceFile, line: usize) -> usize {
+        file.lines()
+            .nth(line - 1)
+            .map_or(0, |line| line.get_text().len())
+    }
+
+    pub struct NoLocatorCalls;
+
+    impl LintRule for NoLocatorCalls {
+        fn id(&self) -> &str {
+            "no-locator-calls"
+        }
+
+        fn description(&self) -> &str {
+            "No locator calls"
+        }
+
+        fn dir_types(&self) -> &[DirType] {
+            &[DirType::Steps, DirType::Interactions]
+        }
+
+        fn options(&self) -> &[&str] {
+            &["locator_class_path"]
+        }
+
+        fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic> {
+            let locator_class_path =
+                match context.get_string_option("locator_class_path", "LOCATOR_CLASS_PATH") {
+                    Some(path) => path,
+                    None => {
+                        return vec![Diagnostic::new(
+                        self.id(),
+                        file.get_path(),
+                        1,
+                        1,
+                        "",
+                        "could not find option locator_class_path or variable LOCATOR_CLASS_PATH",
+                    )];
+                    }
+                };
+            // Accepts both `a.b.Locator` and the full `import a.b.Locator;` statement
+            let locator_class_path = locator_class_path
+                .trim()
+                .trim_start_matches("import ")
+                .trim_end_matches(';')
+                .trim();
+
+            file.get_imports()
+                .iter()
+                .filter(|import| import.get_path().starts_with(locator_class_path))
+                .map(|import| {
+                    file.diagnostic(
+                        self.id(),
+                        import.get_line(),
+                        import.get_column(),
+                        "locators may only be used from pages",
+                    )
+                })
+                .collect()
+        }
+    }
+
+    pub struct PlatformLocatorMethods;
+
+    impl LintRule for PlatformLocatorMethods {
+        fn id(&self) -> &str {
+            "platform-locator-methods"
+        }
+
+        fn description(&self) -> &str {
+            "Use platform Locator methods"
+        }
+
+        fn dir_types(&self) -> &[DirType] {
+            &[DirType::Pages]
+        }
+
+        fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+            let matches = [
+                "Platform",
+                "Children",
+                "ClassName",
+                "ByText",
+                "TidIsPresent",
+            ];
+            let tokens: Vec<&Token> = file.class_tokens().collect();
+
+            // A line passes if any of its code words is a platform method
+            let platform_lines: HashSet<usize> = tokens
+                .iter()
+                .filter(|token| token.is_word())
+                .filter(|token| matches.iter().any(|&m| token.get_text().contains(m)))
+                .map(|token| token.get_line())
+                .collect();
+
+            tokens
+                .windows(2)
+                .filter(|window| {
+                    window[0].is(TokenKind::Identifier, "Locator") && window[1].is_punctuation(".")
+                })
+                .filter(|window| !platform_lines.contains(&window[0].get_line()))
+                .map(|window| {
+                    file.token_diagnostic(self.id(), window[0], "use a platform Locator method")
+                })
+                .collect()
+        }
+    }
+
+    /// Reports suppression comments that no longer silence anything. Its
+    /// diagnostics come from [`crate::process_subdir`] once every other rule
+    /// has checked the file.
+    pub struct UnusedSuppression;
+
+    impl LintRule for UnusedSuppression {
+        fn id(&self) -> &str {
+            "unused-suppression"
+        }
+
+        fn description(&self) -> &str {
+            "No unused suppressions"
+        }
+
+        fn dir_types(&self) -> &[DirType] {
+            &DirType::ALL
+        }
+
+        fn severity(&self) -> Severity {
+            Severity::Warning
+        }
+
+        fn check(&self, _file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+            Vec::new()
+        }
     }
 
     #[cfg(test)]
@@ -411,94 +1950,662 @@ pub mod rules {
         use test::{black_box, Bencher};
 
         use super::{
-            get_log_instead_of_sout, get_no_assert_calls, get_no_locator_calls,
-            get_platform_locator_methods, get_rules,
+            built_in_rules, get_rules, CustomRule, LogInsteadOfSout, NoLocatorCalls,
+            PlatformLocatorMethods,
+        };
+        use crate::{
+            cache::Cache,
+            fixes::{apply_fixes, Fix},
+            get_project_root,
+            lint_config::LintConfig,
+            process_project, process_subdir, Config, Diagnostic, DirType, LintRule, Project,
+            RuleContext, Rules, SourceFile, Subdir,
         };
-        use crate::{get_project_root, process_subdir, Config, Project, Rules};
         use dotenv::dotenv;
+        use std::{
+            fs,
+            path::{Path, PathBuf},
+            sync::{Arc, OnceLock},
+        };
 
         fn get_path() -> String {
             dotenv().ok();
             std::env::var("REPOSITORY_PATH").unwrap()
         }
 
-        #[bench]
-        fn bench_all_rules(b: &mut Bencher) {
+        pub(super) fn test_project() -> Project {
+            let subdirs = DirType::ALL
+                .into_iter()
+                .map(|subdir_type| Subdir {
+                    path: std::env::temp_dir().into_boxed_path(),
+                    subdir_type,
+                })
+                .collect();
+
+            Project {
+                feature_being_tested: "test".to_owned(),
+                follow_symlinks: false,
+                subdirs,
+                dir_type_paths: Vec::new(),
+                step_index: Arc::default(),
+            }
+        }
+
+        fn no_assert_calls() -> CustomRule {
+            built_in_rules()
+                .into_iter()
+                .find(|rule| rule.id() == "no-assert-calls")
+                .unwrap()
+        }
+
+        fn check(rule: impl LintRule, dir_type: DirType, contents: &str) -> Vec<Diagnostic> {
+            let project = test_project();
+            let subdir = project.get_subdir(dir_type).unwrap();
+            let file = SourceFile::from_contents(Path::new("Test.java"), dir_type, contents);
+
+            rule.check(&file, &RuleContext::new(&project, subdir))
+        }
+
+        #[test]
+        fn log_instead_of_sout_reports_line_and_column() {
+            let diagnostics = check(
+                LogInsteadOfSout,
+                DirType::Steps,
+                "import a.b;\n\npublic class Sout {\n    // System.out.println(\"a\");\n    System.out.println(\"b\");\n}\n",
+            );
+
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].get_rule_id(), "log-instead-of-sout");
+            assert_eq!(diagnostics[0].get_line(), 5);
+            assert_eq!(diagnostics[0].get_column(), 5);
+            assert_eq!(diagnostics[0].get_text(), "System.out.println(\"b\");");
+        }
+
+        #[test]
+        fn log_instead_of_sout_fix_uses_logger() {
+            let contents = "package a;\n\nimport a.b.C;\n\npublic class Sout {\n    void a() {\n        System.out.println(\"b\");\n        System.out.print(1);\n        System.out.print(\"a\" + 1);\n        System.out.println();\n    }\n}\n";
+            let diagnostics = check(LogInsteadOfSout, DirType::Steps, contents);
+            let fixes: Vec<&Fix> = diagnostics
+                .iter()
+                .filter_map(|diagnostic| diagnostic.get_fix())
+                .collect();
+
+            assert_eq!(diagnostics.len(), 4);
+            assert_eq!(
+                apply_fixes(contents, &fixes),
+                (
+                    String::from("package a;\n\nimport a.b.C;\nimport org.slf4j.Logger;\nimport org.slf4j.LoggerFactory;\n\npublic class Sout {\n    private static final Logger LOGGER = LoggerFactory.getLogger(Sout.class);\n    void a() {\n        LOGGER.info(\"b\");\n        LOGGER.info(\"{}\", 1);\n        LOGGER.info(\"a\" + 1);\n        System.out.println();\n    }\n}\n"),
+                    3,
+                    0
+                )
+            );
+
+            let fixed = "import org.slf4j.*;\nclass Sout {\n    private Logger LOGGER;\n    void a() { System.out.println(\"a\"); }\n}\n";
+            let diagnostics = check(LogInsteadOfSout, DirType::Steps, fixed);
+            assert_eq!(diagnostics[0].get_fix().unwrap().get_edits().len(), 1);
+        }
+
+        #[test]
+        fn log_instead_of_sout_fix_keeps_crlf_line_endings() {
+            let contents = "import a.b.C;\r\n\r\npublic class Sout {\r\n    void a() { System.out.println(\"a\"); }\r\n}\r\n";
+            let diagnostics = check(LogInsteadOfSout, DirType::Steps, contents);
+            let fixes: Vec<&Fix> = diagnostics
+                .iter()
+                .filter_map(|diagnostic| diagnostic.get_fix())
+                .collect();
+
+            assert_eq!(
+                apply_fixes(contents, &fixes).0,
+                "import a.b.C;\r\nimport org.slf4j.Logger;\r\nimport org.slf4j.LoggerFactory;\r\n\r\npublic class Sout {\r\n    private static final Logger LOGGER = LoggerFactory.getLogger(Sout.class);\r\n    void a() { LOGGER.info(\"a\"); }\r\n}\r\n"
+            );
+        }
+
+        #[test]
+        fn log_instead_of_sout_fix_skips_class_literals() {
+            let contents = "import a.b.C;\n\n@RunWith(Cucumber.class)\npublic class Sout {\n    void a() { System.out.println(\"a\"); }\n}\n";
+            let diagnostics = check(LogInsteadOfSout, DirType::Steps, contents);
+            let fixes: Vec<&Fix> = diagnostics
+                .iter()
+                .filter_map(|diagnostic| diagnostic.get_fix())
+                .collect();
+
+            assert_eq!(
+                apply_fixes(contents, &fixes).0,
+                "import a.b.C;\nimport org.slf4j.Logger;\nimport org.slf4j.LoggerFactory;\n\n@RunWith(Cucumber.class)\npublic class Sout {\n    private static final Logger LOGGER = LoggerFactory.getLogger(Sout.class);\n    void a() { LOGGER.info(\"a\"); }\n}\n"
+            );
+        }
+
+        #[test]
+        fn configure_warns_when_a_custom_rule_replaces_fixes() {
+            let configure = |id: &str| {
+                let mut rules = get_rules();
+                rules
+                    .configure(
+                        LintConfig::parse(&format!(
+                            "[[custom_rules]]\nid = \"{id}\"\nmessage = \"m\"\nforbidden = 'System'\n"
+                        ))
+                        .unwrap(),
+                    )
+                    .unwrap();
+                rules.get_warnings().to_vec()
+            };
+
+            assert_eq!(
+                configure("log-instead-of-sout"),
+                vec![String::from(
+                    "custom rule 'log-instead-of-sout' replaces a rule with fixes, so what it reports can't be fixed"
+                )]
+            );
+            assert!(configure("no-assert-calls").is_empty());
+        }
+
+        #[test]
+        fn configure_rejects_unknown_rule_options() {
+            let configure = |toml: &str| get_rules().configure(LintConfig::parse(toml).unwrap());
+
+            assert!(configure("[rules.no-locator-calls]\nlocator_class_path = \"a.B\"\n").is_ok());
+            let err = configure("[rules.no-locator-calls]\nenable = false\n").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "unknown option 'enable' for rule 'no-locator-calls', expected one of enabled, severity, dir_types, locator_class_path"
+            );
+            assert!(configure("[rules.no-assert-calls]\nseverty = \"error\"\n").is_err());
+
+            let mut rules = get_rules();
+            rules
+                .configure(
+                    LintConfig::parse(
+                        "[[custom_rules]]\nid = \"no-assert-calls\"\nmessage = \"m\"\nforbidden = 'assert'\nseverity = \"off\"\n",
+                    )
+                    .unwrap(),
+                )
+                .unwrap();
+            let rule = rules
+                .get_rules()
+                .iter()
+                .find(|rule| rule.id() == "no-assert-calls")
+                .unwrap();
+            assert!(!rules.is_enabled(rule.as_ref()));
+        }
+
+        #[test]
+        fn no_assert_calls_ignores_imports() {
+            let diagnostics = check(
+                no_assert_calls(),
+                DirType::Steps,
+                "import static org.junit.Assert.assertTrue;\npublic class Assert {\n    void a() { assertTrue(true); }\n}\n",
+            );
+
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].get_line(), 3);
+            assert_eq!(diagnostics[0].get_column(), 16);
+        }
+
+        #[test]
+        fn no_assert_calls_only_matches_assertions() {
+            let contents = "public class Steps {\n    void a() {\n        assert files != null;\n        Assert.assertEquals(1, files);\n        assertionHelper();\n        isAsserted = reassert(files);\n    }\n}\n";
+            let lines: Vec<usize> = check(no_assert_calls(), DirType::Steps, contents)
+                .iter()
+                .map(Diagnostic::get_line)
+                .collect();
+
+            assert_eq!(lines, vec![3, 4]);
+        }
+
+        #[test]
+        fn class_body_rules_check_enums_and_records() {
+            let enum_steps = "import a.b.C;\npublic enum Steps {\n    A;\n    void a() { assertTrue(true); }\n}\n";
+            let record_steps =
+                "import a.b.C;\npublic record Steps(int a) {\n    void a() { assertTrue(true); }\n}\n";
+
+            for contents in [enum_steps, record_steps] {
+                let diagnostics = check(no_assert_calls(), DirType::Steps, contents);
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(
+                    SourceFile::from_contents(Path::new("Steps.java"), DirType::Steps, contents)
+                        .header_tokens()
+                        .count(),
+                    8
+                );
+            }
+        }
+
+        #[test]
+        fn rules_ignore_comments_and_strings() {
+            let contents = "public class Steps {\n    /*\n     * assert and System.out.println here\n     */\n    void a() {\n        log.info(\"assert that System.out.println is gone\"); // assert\n    }\n}\n";
+
+            assert!(check(no_assert_calls(), DirType::Steps, contents).is_empty());
+            assert!(check(LogInsteadOfSout, DirType::Steps, contents).is_empty());
+        }
+
+        #[test]
+        fn platform_locator_methods_checks_code_on_the_line() {
+            let diagnostics = check(
+                PlatformLocatorMethods,
+                DirType::Pages,
+                "public class P {\n    Locator a = Locator.byId(\"Platform\");\n    Locator b = Locator.byPlatformId(\"b\");\n}\n",
+            );
+
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].get_line(), 2);
+            assert_eq!(diagnostics[0].get_column(), 17);
+        }
+
+        #[test]
+        fn cached_files_replay_diagnostics_with_current_suppressions() {
+            let root =
+                std::env::temp_dir().join(format!("lint_apptester_{}_cached", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            let path = root.join("Sout.java");
+            let contents = "class Sout {\n    void a() {\n        System.out.println(1);\n        System.out.println(2);\n    }\n}\n";
+            fs::write(&path, contents).unwrap();
+            let project = Project {
+                feature_being_tested: "test".to_owned(),
+                follow_symlinks: false,
+                subdirs: vec![Subdir {
+                    path: root.clone().into_boxed_path(),
+                    subdir_type: DirType::Steps,
+                }],
+                dir_type_paths: Vec::new(),
+                step_index: Arc::default(),
+            };
+            let rules = get_rules();
+            let cache = Cache::load(root.to_str().unwrap(), &rules).unwrap();
+            let lines = |cache: Option<&Cache>| -> Vec<(String, usize)> {
+                process_subdir(&project, &project.subdirs[0], &rules, None, cache)
+                    .unwrap()
+                    .get_rules()
+                    .iter()
+                    .flat_map(|rule| rule.get_diagnostics())
+                    .map(|diagnostic| (diagnostic.get_rule_id().to_owned(), diagnostic.get_line()))
+                    .collect()
+            };
+
+            let uncached = lines(Some(&cache));
+            assert_eq!(uncached.len(), 2);
+            // The file isn't checked again, so this diagnostic comes from the cache
+            let mut diagnostics = cache.get(&path, contents).unwrap();
+            diagnostics
+                .get_mut("log-instead-of-sout")
+                .unwrap()
+                .push(Diagnostic::new("log-instead-of-sout", &path, 5, 1, "", ""));
+            cache.insert(&path, contents, diagnostics);
+            assert_eq!(lines(Some(&cache)).len(), 3);
+            assert_eq!(lines(None), uncached);
+
+            // Suppressions are applied to cached diagnostics too
+            let suppressed = contents.replace(
+                "(2);",
+                "(2); // lint-apptester-disable-line log-instead-of-sout",
+            );
+            fs::write(&path, &suppressed).unwrap();
+            cache.insert(&path, &suppressed, cache.get(&path, contents).unwrap());
+            assert_eq!(
+                lines(Some(&cache)),
+                vec![
+                    (String::from("log-instead-of-sout"), 3),
+                    (String::from("log-instead-of-sout"), 5)
+                ]
+            );
+            fs::remove_dir_all(root).unwrap();
+        }
+
+        fn bench_rules(b: &mut Bencher, rules: Rules) {
             let config =
                 Config::build(["".to_owned(), ".env".to_owned(), get_path()].into_iter()).unwrap();
             let project_root = get_project_root(&config.current_dir).unwrap();
             let project = Project::init(&project_root, "Files").unwrap();
-            let rules = get_rules();
 
             b.iter(black_box(|| {
                 for subdir in project.get_subdirs() {
-                    process_subdir(subdir, &rules).unwrap();
+                    process_subdir(&project, subdir, &rules, None, None).unwrap();
                 }
             }))
         }
 
+        #[bench]
+        fn bench_all_rules(b: &mut Bencher) {
+            bench_rules(b, get_rules());
+        }
+
         #[bench]
         fn bench_rule_log_instead_of_sout(b: &mut Bencher) {
-            let config =
-                Config::build(["".to_owned(), ".env".to_owned(), get_path()].into_iter()).unwrap();
-            let project_root = get_project_root(&config.current_dir).unwrap();
-            let project = Project::init(&project_root, "Files").unwrap();
             let mut rules = Rules::init();
-            rules.add_rule(get_log_instead_of_sout());
-
-            b.iter(black_box(|| {
-                for subdir in project.get_subdirs() {
-                    process_subdir(subdir, &rules).unwrap();
-                }
-            }))
+            rules.add_rule(LogInsteadOfSout);
+            bench_rules(b, rules);
         }
 
         #[bench]
         fn bench_rule_no_assert_calls(b: &mut Bencher) {
-            let config =
-                Config::build(["".to_owned(), ".env".to_owned(), get_path()].into_iter()).unwrap();
-            let project_root = get_project_root(&config.current_dir).unwrap();
-            let project = Project::init(&project_root, "Files").unwrap();
             let mut rules = Rules::init();
-            rules.add_rule(get_no_assert_calls());
-
-            b.iter(black_box(|| {
-                for subdir in project.get_subdirs() {
-                    process_subdir(subdir, &rules).unwrap();
-                }
-            }))
+            rules.add_rule(no_assert_calls());
+            bench_rules(b, rules);
         }
 
         #[bench]
         fn bench_rule_no_locator_calls(b: &mut Bencher) {
-            let config =
-                Config::build(["".to_owned(), ".env".to_owned(), get_path()].into_iter()).unwrap();
-            let project_root = get_project_root(&config.current_dir).unwrap();
-            let project = Project::init(&project_root, "Files").unwrap();
             let mut rules = Rules::init();
-            rules.add_rule(get_no_locator_calls());
+            rules.add_rule(NoLocatorCalls);
+            bench_rules(b, rules);
+        }
 
-            b.iter(black_box(|| {
-                for subdir in project.get_subdirs() {
-                    process_subdir(subdir, &rules).unwrap();
+        #[bench]
+        fn bench_rule_platform_locator_methods(b: &mut Bencher) {
+            let mut rules = Rules::init();
+            rules.add_rule(PlatformLocatorMethods);
+            bench_rules(b, rules);
+        }
+
+        const LARGE_PROJECT_FILES: usize = 250;
+
+        /// A project with many files in every subdir, written to the temp dir
+        /// once per run.
+        fn large_project() -> Project {
+            static ROOT: OnceLock<PathBuf> = OnceLock::new();
+            let root = ROOT.get_or_init(|| {
+                let root = std::env::temp_dir().join("lint_apptester_bench_project");
+                for dir_type in DirType::ALL {
+                    let dir = root.join(dir_type.label()).join("files");
+                    fs::create_dir_all(&dir).unwrap();
+                    for index in 0..LARGE_PROJECT_FILES {
+                        let (name, contents) = match dir_type {
+                            DirType::Features => (
+                                format!("files{index}.feature"),
+                                format!(
+                                    "Feature: Files {index}\n\n{}",
+                                    format!("  Scenario: Open\n    Given I open files {index}\n    Then I should see 2 files\n\n")
+                                        .repeat(10)
+                                ),
+                            ),
+                            _ => (
+                                format!("Files{index}.java"),
+                                format!(
+                                    "package a.b.{}.files;\n\nimport a.b.c.Locator;\n\npublic class Files{index} {{\n    @Given(\"I open files {index}\")\n    public void openFiles() {{}}\n\n{}}}\n",
+                                    dir_type.label(),
+                                    "    private Locator list = Locator.byId(\"list\");\n\n    public void open() {\n        // Open the files\n        System.out.println(\"opening\");\n        assert list != null;\n    }\n\n"
+                                        .repeat(20)
+                                ),
+                            ),
+                        };
+                        fs::write(dir.join(name), contents).unwrap();
+                    }
                 }
+                root
+            });
+
+            let subdirs = DirType::ALL
+                .into_iter()
+                .map(|subdir_type| Subdir {
+                    path: root
+                        .join(subdir_type.label())
+                        .join("files")
+                        .into_boxed_path(),
+                    subdir_type,
+                })
+                .collect();
+            let dir_type_paths = DirType::ALL
+                .into_iter()
+                .map(|dir_type| (dir_type, root.join(dir_type.label())))
+                .collect();
+            Project::from_subdirs(
+                String::from("files"),
+                subdirs,
+                dir_type_paths,
+                Arc::default(),
+            )
+        }
+
+        #[bench]
+        fn bench_large_project(b: &mut Bencher) {
+            let project = large_project();
+            let rules = get_rules();
+
+            b.iter(black_box(|| {
+                process_project(&project, &rules, None, None).unwrap()
             }))
         }
 
+        /// The same work as [`bench_large_project`] on one thread, for comparison.
         #[bench]
-        fn bench_rule_platform_locator_methods(b: &mut Bencher) {
-            let config =
-                Config::build(["".to_owned(), ".env".to_owned(), get_path()].into_iter()).unwrap();
-            let project_root = get_project_root(&config.current_dir).unwrap();
-            let project = Project::init(&project_root, "Files").unwrap();
-            let mut rules = Rules::init();
-            rules.add_rule(get_platform_locator_methods());
+        fn bench_large_project_single_thread(b: &mut Bencher) {
+            let project = large_project();
+            let rules = get_rules();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap();
 
             b.iter(black_box(|| {
-                for subdir in project.get_subdirs() {
-                    process_subdir(subdir, &rules).unwrap();
-                }
+                pool.install(|| process_project(&project, &rules, None, None).unwrap())
             }))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, sync::Arc};
+
+    use crate::{
+        cucumber::{SharedStepIndex, StepIndex},
+        process_project, process_projects,
+        rules::get_rules,
+        Diagnostic, DirType, ExitStatus, FeatureReport, Project, Severity, Subdir,
+    };
+
+    fn temp_tree(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("lint_apptester_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("files/dialogs")).unwrap();
+        fs::write(root.join("files/FilesPage.java"), "").unwrap();
+        fs::write(root.join("files/dialogs/DeleteDialog.java"), "").unwrap();
+        fs::write(root.join("files/dialogs/notes.txt"), "").unwrap();
+        fs::write(root.join("AbstractPage.java"), "").unwrap();
+        root
+    }
+
+    fn relative_files(root: &PathBuf, follow_symlinks: bool) -> Vec<String> {
+        let subdir = Subdir::new(root.to_str().unwrap().to_owned(), DirType::Pages).unwrap();
+        subdir
+            .get_files(follow_symlinks)
+            .unwrap()
+            .iter()
+            .map(|path| {
+                path.strip_prefix(root)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_files_recurses_in_sorted_order() {
+        let root = temp_tree("recurse");
+
+        assert_eq!(
+            relative_files(&root, false),
+            vec![
+                "AbstractPage.java",
+                "files/FilesPage.java",
+                "files/dialogs/DeleteDialog.java",
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn get_files_follows_symlinks_only_when_asked() {
+        let root = temp_tree("symlinks");
+        let shared = root.with_extension("shared");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("SharedPage.java"), "").unwrap();
+        std::os::unix::fs::symlink(&shared, root.join("linked")).unwrap();
+        // A cycle back to the root must not recurse forever
+        std::os::unix::fs::symlink(&root, root.join("files/loop")).unwrap();
+
+        assert_eq!(relative_files(&root, false).len(), 3);
+        assert_eq!(
+            relative_files(&root, true),
+            vec![
+                "AbstractPage.java",
+                "files/FilesPage.java",
+                "files/dialogs/DeleteDialog.java",
+                "linked/SharedPage.java",
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+        fs::remove_dir_all(shared).unwrap();
+    }
+
+    /// A `files` feature with only features and a `common` one with only steps,
+    /// sharing a step index.
+    fn shared_step_projects(name: &str, steps: &str) -> (PathBuf, [Project; 2]) {
+        let root =
+            std::env::temp_dir().join(format!("lint_apptester_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("features/files")).unwrap();
+        fs::create_dir_all(root.join("steps/common")).unwrap();
+        fs::write(
+            root.join("features/files/files.feature"),
+            "Feature: Files\n  Scenario: Open\n    Given I open files\n",
+        )
+        .unwrap();
+        fs::write(root.join("steps/common/CommonSteps.java"), steps).unwrap();
+
+        let subdir = |dir_type: DirType, feature: &str| Subdir {
+            path: root.join(dir_type.label()).join(feature).into_boxed_path(),
+            subdir_type: dir_type,
+        };
+        let step_index = Arc::new(SharedStepIndex::new(
+            StepIndex::DIR_TYPES
+                .into_iter()
+                .map(|dir_type| subdir(dir_type, ""))
+                .collect(),
+            false,
+        ));
+        let projects = [("files", DirType::Features), ("common", DirType::Steps)].map(
+            |(feature, dir_type)| Project {
+                feature_being_tested: feature.to_owned(),
+                follow_symlinks: false,
+                subdirs: vec![subdir(dir_type, feature)],
+                dir_type_paths: Vec::new(),
+                step_index: Arc::clone(&step_index),
+            },
+        );
+
+        (root, projects)
+    }
+
+    fn count_diagnostics(report: &FeatureReport, rule_ids: &[&str]) -> usize {
+        report
+            .get_subdirs()
+            .iter()
+            .flat_map(|subdir| subdir.get_rules())
+            .filter(|rule| rule_ids.contains(&rule.get_id()))
+            .map(|rule| rule.get_diagnostics().len())
+            .sum()
+    }
+
+    #[test]
+    fn step_definitions_are_shared_between_features() {
+        let (root, projects) = shared_step_projects(
+            "shared_steps",
+            "class CommonSteps {\n    @Given(\"I open files\")\n    void open() {}\n}\n",
+        );
+
+        let rules = get_rules();
+        for project in &projects {
+            let report = process_project(project, &rules, None, None).unwrap();
+            assert_eq!(
+                count_diagnostics(&report, &["undefined-steps", "unused-step-definitions"]),
+                0,
+                "{}",
+                project.get_feature_being_tested()
+            );
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn process_projects_lints_files_the_step_index_read_without_reading_them_again() {
+        let (root, projects) = shared_step_projects(
+            "indexed_files",
+            "class CommonSteps {\n    @Given(\"I open files\")\n    void open() { System.out.println(\"a\"); }\n}\n",
+        );
+        let rules = get_rules();
+        projects[0].get_step_index();
+        // Changes after the index read the files aren't seen until it's reset
+        fs::write(
+            root.join("steps/common/CommonSteps.java"),
+            "class CommonSteps {}\n",
+        )
+        .unwrap();
+
+        let reports = process_projects(&projects, &rules, None, None).unwrap();
+        let features: Vec<&str> = reports.iter().map(FeatureReport::get_feature).collect();
+        assert_eq!(features, vec!["files", "common"]);
+        assert_eq!(count_diagnostics(&reports[0], &["undefined-steps"]), 0);
+        assert_eq!(count_diagnostics(&reports[1], &["log-instead-of-sout"]), 1);
+
+        projects[1].reset_step_index();
+        let reports = process_projects(&projects, &rules, None, None).unwrap();
+        assert_eq!(count_diagnostics(&reports[0], &["undefined-steps"]), 1);
+        assert_eq!(count_diagnostics(&reports[1], &["log-instead-of-sout"]), 0);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn diagnostics_with(severities: &[Severity]) -> Vec<Diagnostic> {
+        severities
+            .iter()
+            .map(|&severity| {
+                let mut diagnostic =
+                    Diagnostic::new("rule", &PathBuf::from("A.java"), 1, 1, "", "");
+                diagnostic.set_severity(severity);
+                diagnostic
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exit_status_separates_warnings_from_errors() {
+        let info = diagnostics_with(&[Severity::Info]);
+        let warnings = diagnostics_with(&[Severity::Warning, Severity::Warning]);
+        let errors = diagnostics_with(&[Severity::Warning, Severity::Error]);
+
+        assert_eq!(
+            ExitStatus::from_diagnostics(&info, None, false),
+            ExitStatus::Clean
+        );
+        assert_eq!(
+            ExitStatus::from_diagnostics(&warnings, None, false),
+            ExitStatus::Warnings
+        );
+        assert_eq!(
+            ExitStatus::from_diagnostics(&errors, None, false),
+            ExitStatus::Errors
+        );
+    }
+
+    #[test]
+    fn exit_status_escalates_warnings() {
+        let warnings = diagnostics_with(&[Severity::Warning, Severity::Warning]);
+
+        assert_eq!(
+            ExitStatus::from_diagnostics(&warnings, Some(2), false),
+            ExitStatus::Warnings
+        );
+        assert_eq!(
+            ExitStatus::from_diagnostics(&warnings, Some(1), false),
+            ExitStatus::Errors
+        );
+        assert_eq!(
+            ExitStatus::from_diagnostics(&warnings, None, true),
+            ExitStatus::Errors
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::{DirType, Result, Severity};
+
+pub const LINT_CONFIG_FILE_NAME: &str = "lint_apptester.toml";
+
+/// Project level configuration read from `lint_apptester.toml` at the project root.
+///
+/// ```toml
+/// [rules.log-instead-of-sout]
+/// severity = "warn"
+/// dir_types = ["Pages", "Steps"]
+/// logger_call = "log.info"
+/// logger_field = "private static final Logger log = LogManager.getLogger({class}.class);"
+/// logger_imports = ["org.apache.logging.log4j.LogManager", "org.apache.logging.log4j.Logger"]
+///
+/// [rules.no-locator-calls]
+/// locator_class_path = "import a.b.c.Locator"
+///
+/// [rules.no-assert-calls]
+/// enabled = false
+///
+/// [rules.layering]
+/// allowed = { steps = ["interactions"], interactions = ["pages"], pages = [] }
+///
+/// [[custom_rules]]
+/// id = "no-thread-sleep"
+/// message = "wait for an element instead of sleeping"
+/// severity = "warn"
+/// globs = ["steps/**/*.java", "interactions/**/*.java"]
+/// scope = "class_body"
+/// forbidden = 'Thread\.sleep\('
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    rules: HashMap<String, RuleConfig>,
+    #[serde(default)]
+    custom_rules: Vec<CustomRuleConfig>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct RuleConfig {
+    enabled: Option<bool>,
+    severity: Option<RuleLevel>,
+    dir_types: Option<Vec<DirType>>,
+    /// Any other keys are rule specific options
+    #[serde(flatten)]
+    options: toml::Table,
+}
+
+/// A rule declared in the config file: a regex that must not, or must, match
+/// the code in part of each file. Declaring one with the id of a built-in rule
+/// replaces that rule.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRuleConfig {
+    id: String,
+    /// Defaults to the message
+    description: Option<String>,
+    message: String,
+    /// `off` declares the rule without running it
+    #[serde(default)]
+    severity: Option<RuleLevel>,
+    /// Every dir type if neither these nor globs are given
+    dir_types: Option<Vec<DirType>>,
+    /// Matched against the end of each file's path
+    #[serde(default)]
+    globs: Vec<String>,
+    forbidden: Option<String>,
+    required: Option<String>,
+    #[serde(default)]
+    scope: RuleScope,
+}
+
+/// The part of a file a custom rule's regex is matched against. Comments and
+/// the contents of string literals are never matched in Java or JavaScript.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleScope {
+    /// The package and import declarations
+    Imports,
+    /// Everything from the class declaration on
+    ClassBody,
+    #[default]
+    File,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Error,
+    #[serde(alias = "warning")]
+    Warn,
+    Info,
+    Off,
+}
+
+impl LintConfig {
+    /// Reads the config file in `project_root`, or returns the default config if
+    /// there isn't one.
+    pub fn load(project_root: &str) -> Result<Self> {
+        let path = Path::new(project_root).join(LINT_CONFIG_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::parse(&fs::read_to_string(&path)?)
+            .map_err(|err| format!("could not parse {}: {err}", path.display()).into())
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    pub fn get_rule_config(&self, rule_id: &str) -> Option<&RuleConfig> {
+        self.rules.get(rule_id)
+    }
+
+    pub fn get_rule_ids(&self) -> impl Iterator<Item = &String> {
+        self.rules.keys()
+    }
+
+    pub fn get_custom_rules(&self) -> &[CustomRuleConfig] {
+        &self.custom_rules
+    }
+}
+
+impl CustomRuleConfig {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_description(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.message)
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_severity(&self) -> Option<Severity> {
+        self.severity?.get_severity()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.severity != Some(RuleLevel::Off)
+    }
+
+    pub fn get_dir_types(&self) -> Option<&[DirType]> {
+        self.dir_types.as_deref()
+    }
+
+    pub fn get_globs(&self) -> &[String] {
+        &self.globs
+    }
+
+    pub fn get_forbidden(&self) -> Option<&str> {
+        self.forbidden.as_deref()
+    }
+
+    pub fn get_required(&self) -> Option<&str> {
+        self.required.as_deref()
+    }
+
+    pub fn get_scope(&self) -> RuleScope {
+        self.scope
+    }
+}
+
+impl RuleLevel {
+    /// The severity to report with, or `None` for `off`.
+    pub fn get_severity(self) -> Option<Severity> {
+        match self {
+            RuleLevel::Error => Some(Severity::Error),
+            RuleLevel::Warn => Some(Severity::Warning),
+            RuleLevel::Info => Some(Severity::Info),
+            RuleLevel::Off => None,
+        }
+    }
+}
+
+impl RuleConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true) && self.severity != Some(RuleLevel::Off)
+    }
+
+    /// The configured severity, or `None` to keep the rule's default.
+    pub fn get_severity(&self) -> Option<Severity> {
+        self.severity?.get_severity()
+    }
+
+    pub fn get_dir_types(&self) -> Option<&[DirType]> {
+        self.dir_types.as_deref()
+    }
+
+    pub fn get_option(&self, key: &str) -> Option<&toml::Value> {
+        self.options.get(key)
+    }
+
+    pub fn get_option_keys(&self) -> impl Iterator<Item = &String> {
+        self.options.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LintConfig, RuleScope};
+    use crate::{DirType, Severity};
+
+    #[test]
+    fn parse_reads_rule_settings_and_options() {
+        let config = LintConfig::parse(
+            r#"
+            [rules.log-instead-of-sout]
+            severity = "error"
+            dir_types = ["Pages", "steps"]
+
+            [rules.no-locator-calls]
+            locator_class_path = "import a.b.Locator"
+
+            [rules.no-assert-calls]
+            severity = "off"
+            "#,
+        )
+        .unwrap();
+
+        let sout = config.get_rule_config("log-instead-of-sout").unwrap();
+        assert!(sout.is_enabled());
+        assert_eq!(sout.get_severity(), Some(Severity::Error));
+        assert_eq!(
+            sout.get_dir_types(),
+            Some(&[DirType::Pages, DirType::Steps][..])
+        );
+
+        let locator = config.get_rule_config("no-locator-calls").unwrap();
+        assert_eq!(locator.get_severity(), None);
+        assert_eq!(
+            locator
+                .get_option("locator_class_path")
+                .and_then(|value| value.as_str()),
+            Some("import a.b.Locator")
+        );
+
+        assert!(!config
+            .get_rule_config("no-assert-calls")
+            .unwrap()
+            .is_enabled());
+    }
+
+    #[test]
+    fn parse_reads_custom_rules() {
+        let config = LintConfig::parse(
+            r#"
+            [[custom_rules]]
+            id = "no-thread-sleep"
+            message = "wait for an element instead"
+            severity = "warn"
+            dir_types = ["steps"]
+            scope = "class_body"
+            forbidden = 'Thread\.sleep'
+            "#,
+        )
+        .unwrap();
+
+        let rule = &config.get_custom_rules()[0];
+        assert_eq!(rule.get_id(), "no-thread-sleep");
+        assert_eq!(rule.get_description(), "wait for an element instead");
+        assert_eq!(rule.get_severity(), Some(Severity::Warning));
+        assert_eq!(rule.get_scope(), RuleScope::ClassBody);
+        assert_eq!(rule.get_forbidden(), Some("Thread\\.sleep"));
+        assert!(LintConfig::parse(
+            "[[custom_rules]]\nid = \"a\"\nmessage = \"b\"\nscope = \"method\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_rejects_unknown_severity() {
+        assert!(LintConfig::parse("[rules.a]\nseverity = \"fatal\"\n").is_err());
+    }
+}
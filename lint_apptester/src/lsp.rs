@@ -0,0 +1,572 @@
+//! `--lsp`: a language server over stdio that lints open documents as they
+//! change, so editors show diagnostics and offer fixes.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification as _, PublishDiagnostics, ShowMessage,
+    },
+    request::{CodeActionRequest, Request as _},
+    CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams, MessageType,
+    NumberOrString, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    ShowMessageParams, TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    fixes::{apply_fixes, Edit, Fix},
+    lint_config::{LintConfig, LINT_CONFIG_FILE_NAME},
+    lint_source,
+    rules::get_rules,
+    Diagnostic, Project, Result, Rules, Severity, Subdir, LINTABLE_EXTENSIONS,
+};
+
+const SOURCE: &str = "lint_apptester";
+
+/// The open documents of an editor and the projects they belong to.
+pub struct Server {
+    project_root: String,
+    projects: Vec<Project>,
+    rules: Rules,
+    documents: HashMap<Url, String>,
+}
+
+/// Serves editor requests over stdin and stdout until the editor shuts the
+/// server down.
+pub fn run(project_root: &str, projects: Vec<Project>, rules: Rules) -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    Server::new(project_root, projects, rules).serve(&connection)?;
+
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![
+                CodeActionKind::QUICKFIX,
+                CodeActionKind::SOURCE_FIX_ALL,
+            ]),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+impl Server {
+    pub fn new(project_root: &str, projects: Vec<Project>, rules: Rules) -> Self {
+        Self {
+            project_root: String::from(project_root),
+            projects,
+            rules,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn serve(&mut self, connection: &Connection) -> Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    connection
+                        .sender
+                        .send(Message::Response(self.handle_request(request)))?;
+                }
+                Message::Notification(notification) => {
+                    for notification in self.handle_notification(notification) {
+                        connection
+                            .sender
+                            .send(Message::Notification(notification))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            CodeActionRequest::METHOD => {
+                match serde_json::from_value::<CodeActionParams>(request.params) {
+                    Ok(params) => Response::new_ok(request.id, self.code_actions(&params)),
+                    Err(err) => Response::new_err(
+                        request.id,
+                        ErrorCode::InvalidParams as i32,
+                        err.to_string(),
+                    ),
+                }
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request '{method}'"),
+            ),
+        }
+    }
+
+    /// Updates the open documents and returns the diagnostics to publish. An
+    /// error, e.g. from saving an invalid config, is shown to the user and the
+    /// server carries on as before.
+    pub fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let method = notification.method.clone();
+        self.try_handle_notification(notification)
+            .unwrap_or_else(|err| {
+                vec![show_message(
+                    MessageType::ERROR,
+                    &format!("{SOURCE}: {method}: {err}"),
+                )]
+            })
+    }
+
+    fn try_handle_notification(&mut self, notification: Notification) -> Result<Vec<Notification>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                Ok(vec![self.publish(&uri)])
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // Documents are synced in full, so the last change is the whole text
+                match params.content_changes.into_iter().last() {
+                    Some(change) => {
+                        self.documents.insert(uri.clone(), change.text);
+                        Ok(vec![self.publish(&uri)])
+                    }
+                    None => Ok(Vec::new()),
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let params: DidSaveTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.saved(&params.text_document.uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                Ok(vec![publish_diagnostics(uri, Vec::new())])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Reads what other open documents depend on again after a file is saved:
+    /// the rule configuration, or the step index for cross-file rules.
+    fn saved(&mut self, uri: &Url) -> Result<Vec<Notification>> {
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(Vec::new());
+        };
+
+        if path
+            .file_name()
+            .is_some_and(|name| name == LINT_CONFIG_FILE_NAME)
+        {
+            // The rules only change once the whole config is valid
+            let mut rules = get_rules();
+            rules.configure(LintConfig::load(&self.project_root)?)?;
+            rules.check_projects(&self.projects)?;
+            self.rules = rules;
+            let warnings =
+                self.rules.get_warnings().iter().map(|warning| {
+                    show_message(MessageType::WARNING, &format!("{SOURCE}: {warning}"))
+                });
+            let published = self.documents.keys().map(|uri| self.publish(uri));
+            return Ok(warnings.chain(published).collect());
+        } else {
+            let path = normalise(&path);
+            let step_index_project = self.projects.iter().find(|project| {
+                project
+                    .get_shared_step_index()
+                    .get_dirs()
+                    .iter()
+                    .any(|dir| path.starts_with(normalise(dir.get_path())))
+            });
+            match step_index_project {
+                Some(project) => project.reset_step_index(),
+                None => return Ok(Vec::new()),
+            }
+        }
+
+        Ok(self.documents.keys().map(|uri| self.publish(uri)).collect())
+    }
+
+    fn publish(&self, uri: &Url) -> Notification {
+        let contents = self.documents.get(uri).map_or("", String::as_str);
+        let diagnostics = self
+            .lint(uri)
+            .iter()
+            .map(|diagnostic| to_lsp_diagnostic(diagnostic, contents))
+            .collect();
+
+        publish_diagnostics(uri.clone(), diagnostics)
+    }
+
+    /// Lints the open document at `uri`, if it's in one of the projects' subdirs.
+    fn lint(&self, uri: &Url) -> Vec<Diagnostic> {
+        let (Some(contents), Ok(path)) = (self.documents.get(uri), uri.to_file_path()) else {
+            return Vec::new();
+        };
+        match self.find_subdir(&path) {
+            Some((project, subdir)) => lint_source(project, subdir, &self.rules, &path, contents),
+            None => Vec::new(),
+        }
+    }
+
+    fn find_subdir(&self, path: &Path) -> Option<(&Project, &Subdir)> {
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_str()
+            .unwrap_or_default();
+        if !LINTABLE_EXTENSIONS.contains(&extension) {
+            return None;
+        }
+
+        let path = normalise(path);
+        self.projects.iter().find_map(|project| {
+            project
+                .subdirs
+                .iter()
+                .find(|subdir| path.starts_with(normalise(subdir.get_path())))
+                .map(|subdir| (project, subdir))
+        })
+    }
+
+    /// A quick fix for each fixable diagnostic in the requested range, and one
+    /// action fixing every problem in the document.
+    fn code_actions(&self, params: &CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let uri = &params.text_document.uri;
+        let Some(contents) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let wants = |kind: &CodeActionKind| {
+            params.context.only.as_ref().is_none_or(|only| {
+                only.iter()
+                    .any(|wanted| kind.as_str().starts_with(wanted.as_str()))
+            })
+        };
+
+        let diagnostics = self.lint(uri);
+        let mut actions = Vec::new();
+        if wants(&CodeActionKind::QUICKFIX) {
+            for diagnostic in &diagnostics {
+                let Some(fix) = diagnostic.get_fix() else {
+                    continue;
+                };
+                let lsp_diagnostic = to_lsp_diagnostic(diagnostic, contents);
+                let range = lsp_diagnostic.range;
+                if range.end < params.range.start || params.range.end < range.start {
+                    continue;
+                }
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: String::from(fix.get_description()),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![lsp_diagnostic]),
+                    edit: Some(workspace_edit(uri, text_edits(fix, contents))),
+                    is_preferred: Some(true),
+                    ..Default::default()
+                }));
+            }
+        }
+
+        let fixes: Vec<&Fix> = diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.get_fix())
+            .collect();
+        if !fixes.is_empty() && wants(&CodeActionKind::SOURCE_FIX_ALL) {
+            let (fixed, _, _) = apply_fixes(contents, &fixes);
+            let whole_document = Range::new(Position::new(0, 0), end_position(contents));
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Fix all {SOURCE} problems"),
+                kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+                edit: Some(workspace_edit(
+                    uri,
+                    vec![TextEdit::new(whole_document, fixed)],
+                )),
+                ..Default::default()
+            }));
+        }
+
+        actions
+    }
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Notification {
+    Notification::new(
+        String::from(PublishDiagnostics::METHOD),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        },
+    )
+}
+
+fn show_message(typ: MessageType, message: &str) -> Notification {
+    Notification::new(
+        String::from(ShowMessage::METHOD),
+        ShowMessageParams {
+            typ,
+            message: String::from(message),
+        },
+    )
+}
+
+fn workspace_edit(uri: &Url, edits: Vec<TextEdit>) -> WorkspaceEdit {
+    WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), edits)])),
+        ..Default::default()
+    }
+}
+
+/// Converts a diagnostic, whose range is its text from its column, or the rest
+/// of the line if the text isn't there.
+pub fn to_lsp_diagnostic(diagnostic: &Diagnostic, contents: &str) -> lsp_types::Diagnostic {
+    let line_index = diagnostic.get_line().saturating_sub(1);
+    let line = contents.lines().nth(line_index).unwrap_or_default();
+    let column = diagnostic.get_column().saturating_sub(1).min(line.len());
+    let text = diagnostic.get_text();
+    let end_column = if !text.is_empty() && line[column..].starts_with(text) {
+        column + text.len()
+    } else {
+        line.len()
+    };
+
+    lsp_types::Diagnostic {
+        range: Range::new(
+            position(line_index, line, column),
+            position(line_index, line, end_column),
+        ),
+        severity: Some(match diagnostic.get_severity() {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Info => DiagnosticSeverity::INFORMATION,
+        }),
+        code: Some(NumberOrString::String(String::from(
+            diagnostic.get_rule_id(),
+        ))),
+        source: Some(String::from(SOURCE)),
+        message: String::from(diagnostic.get_message()),
+        ..Default::default()
+    }
+}
+
+fn text_edits(fix: &Fix, contents: &str) -> Vec<TextEdit> {
+    let lines: Vec<&str> = contents.split('\n').collect();
+    let edit_position = |line: usize, column: usize| {
+        let line_index = line.saturating_sub(1);
+        match lines.get(line_index) {
+            Some(text) => position(line_index, text, column),
+            None => end_position(contents),
+        }
+    };
+
+    fix.get_edits()
+        .iter()
+        .map(|edit: &Edit| {
+            TextEdit::new(
+                Range::new(
+                    edit_position(edit.get_line(), edit.get_column()),
+                    edit_position(edit.get_end_line(), edit.get_end_column()),
+                ),
+                String::from(edit.get_replacement()),
+            )
+        })
+        .collect()
+}
+
+/// The position of a byte offset in a line. Editors count columns in UTF-16
+/// code units.
+fn position(line_index: usize, line: &str, byte_column: usize) -> Position {
+    let character: usize = line
+        .char_indices()
+        .take_while(|(index, _)| *index < byte_column)
+        .map(|(_, character)| character.len_utf16())
+        .sum();
+    Position::new(line_index as u32, character as u32)
+}
+
+fn end_position(contents: &str) -> Position {
+    let line_index = contents.matches('\n').count();
+    let last_line = contents.rsplit('\n').next().unwrap_or_default();
+    position(line_index, last_line, last_line.len())
+}
+
+fn normalise(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use lsp_server::{Notification, Request, RequestId};
+    use lsp_types::{
+        CodeActionContext, CodeActionOrCommand, CodeActionParams, DidOpenTextDocumentParams,
+        MessageType, NumberOrString, Position, PublishDiagnosticsParams, Range, ShowMessageParams,
+        TextDocumentIdentifier, TextDocumentItem, Url,
+    };
+
+    use super::Server;
+    use crate::{lint_config::LINT_CONFIG_FILE_NAME, rules::get_rules, DirType, Project, Subdir};
+
+    #[test]
+    fn server_publishes_diagnostics_and_fixes_for_open_documents() {
+        let root = std::env::temp_dir().join(format!("lint_apptester_{}_lsp", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("steps")).unwrap();
+        let project = Project {
+            feature_being_tested: "files".to_owned(),
+            follow_symlinks: false,
+            subdirs: vec![Subdir {
+                path: root.join("steps").into_boxed_path(),
+                subdir_type: DirType::Steps,
+            }],
+            dir_type_paths: Vec::new(),
+            step_index: Arc::default(),
+        };
+        let mut server = Server::new(root.to_str().unwrap(), vec![project], get_rules());
+        let uri = Url::from_file_path(root.join("steps/Sout.java")).unwrap();
+
+        let notifications = server.handle_notification(Notification::new(
+            String::from("textDocument/didOpen"),
+            DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    String::from("java"),
+                    1,
+                    String::from("class Sout {\n    void é() { System.out.println(1); }\n}\n"),
+                ),
+            },
+        ));
+        let published: PublishDiagnosticsParams =
+            serde_json::from_value(notifications[0].params.clone()).unwrap();
+        let diagnostics: Vec<_> = published
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                diagnostic.code == Some(NumberOrString::String("log-instead-of-sout".into()))
+            })
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+        // `é` is two bytes but one UTF-16 code unit
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 15), Position::new(1, 39))
+        );
+
+        let response = server.handle_request(Request::new(
+            RequestId::from(1),
+            String::from("textDocument/codeAction"),
+            CodeActionParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                range: Range::new(Position::new(1, 20), Position::new(1, 20)),
+                context: CodeActionContext::default(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        ));
+        let actions: Vec<CodeActionOrCommand> =
+            serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(actions.len(), 2);
+        let CodeActionOrCommand::CodeAction(quick_fix) = &actions[0] else {
+            panic!("expected a code action");
+        };
+        let edits = &quick_fix.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert!(edits.iter().any(|edit| edit.new_text == "LOGGER.info"
+            && edit.range == Range::new(Position::new(1, 15), Position::new(1, 33))));
+
+        let notifications = server.handle_notification(Notification::new(
+            String::from("textDocument/didClose"),
+            serde_json::json!({ "textDocument": { "uri": uri } }),
+        ));
+        let published: PublishDiagnosticsParams =
+            serde_json::from_value(notifications[0].params.clone()).unwrap();
+        assert!(published.diagnostics.is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn server_keeps_its_rules_when_a_saved_config_is_invalid() {
+        let root =
+            std::env::temp_dir().join(format!("lint_apptester_{}_lsp_config", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("steps")).unwrap();
+        let config = root.join(LINT_CONFIG_FILE_NAME);
+        fs::write(&config, "[rules.log-instead-of-sout]\nenable = false\n").unwrap();
+        let project = Project {
+            feature_being_tested: "files".to_owned(),
+            follow_symlinks: false,
+            subdirs: vec![Subdir {
+                path: root.join("steps").into_boxed_path(),
+                subdir_type: DirType::Steps,
+            }],
+            dir_type_paths: Vec::new(),
+            step_index: Arc::default(),
+        };
+        let mut server = Server::new(root.to_str().unwrap(), vec![project], get_rules());
+        let notification = |method: &str, params: serde_json::Value| {
+            Notification::new(String::from(method), params)
+        };
+
+        let notifications = server.handle_notification(notification(
+            "textDocument/didSave",
+            serde_json::json!({ "textDocument": { "uri": Url::from_file_path(&config).unwrap() } }),
+        ));
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].method, "window/showMessage");
+        let shown: ShowMessageParams =
+            serde_json::from_value(notifications[0].params.clone()).unwrap();
+        assert_eq!(shown.typ, MessageType::ERROR);
+        assert!(shown.message.contains("unknown option 'enable'"));
+
+        let uri = Url::from_file_path(root.join("steps/Sout.java")).unwrap();
+        let notifications = server.handle_notification(notification(
+            "textDocument/didOpen",
+            serde_json::json!({ "textDocument": {
+                "uri": uri,
+                "languageId": "java",
+                "version": 1,
+                "text": "class Sout {\n    void a() { System.out.println(1); }\n}\n"
+            } }),
+        ));
+        let published: PublishDiagnosticsParams =
+            serde_json::from_value(notifications[0].params.clone()).unwrap();
+        assert!(published.diagnostics.iter().any(|diagnostic| {
+            diagnostic.code == Some(NumberOrString::String("log-instead-of-sout".into()))
+        }));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,40 +1,249 @@
-#![feature(test)]
-#![feature(string_remove_matches)]
-
 use dotenv::dotenv;
-use std::{env, process};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process,
+};
 
-use lint_apptester::{get_project_root, process_subdir, rules::get_rules, Config, Project, Result};
+use lint_apptester::{
+    baseline::Baseline,
+    cache::Cache,
+    changes::ChangeSet,
+    fixes::fix_files,
+    formatters::{get_formatter, CompactFormatter, Formatter},
+    get_project_root,
+    lint_config::LintConfig,
+    lsp, process_projects,
+    rules::get_rules,
+    watch::watch,
+    Config, ExitStatus, FixMode, OutputFormat, Project, Report, Result, Rules,
+};
 
 fn main() {
     dotenv().ok();
 
     let config = Config::build(env::args()).unwrap_or_else(|err| {
         eprintln!("apptester_lint: problem with arguments: {err}");
-        process::exit(1);
+        process::exit(ExitStatus::Failure.code());
     });
 
     let project_root = get_project_root(config.get_current_dir()).unwrap_or_else(|err| {
         eprintln!("apptester_lint: problem getting project root: {err}");
-        process::exit(1);
+        process::exit(ExitStatus::Failure.code());
     });
-    let project = Project::init(&project_root, config.get_feature()).unwrap_or_else(|err| {
+    let projects = match config.get_feature() {
+        Some(feature) => Project::init(&project_root, feature).map(|project| vec![project]),
+        None => Project::discover(&project_root),
+    };
+    let projects = projects.unwrap_or_else(|err| {
         eprintln!("apptester_lint: problem initialising: {err}");
-        process::exit(1);
+        process::exit(ExitStatus::Failure.code());
     });
 
-    if let Err(err) = run(project) {
-        eprintln!("apptester_lint: application error: {err}");
-        process::exit(1);
+    match run(&config, &project_root, projects) {
+        Ok(exit_status) => process::exit(exit_status.code()),
+        Err(err) => {
+            eprintln!("apptester_lint: application error: {err}");
+            process::exit(ExitStatus::Failure.code());
+        }
     }
 }
 
-fn run(project: Project) -> Result<()> {
-    let rules = get_rules();
+fn run(config: &Config, project_root: &str, projects: Vec<Project>) -> Result<ExitStatus> {
+    let mut rules = get_rules();
+    rules.configure(LintConfig::load(project_root)?)?;
+    rules.check_projects(&projects)?;
+    for warning in rules.get_warnings() {
+        eprintln!("apptester_lint: warning: {warning}");
+    }
+
+    if config.get_lsp() {
+        lsp::run(project_root, projects, rules)?;
+        return Ok(ExitStatus::Clean);
+    }
+    if config.get_watch() {
+        return run_watch(config, project_root, &projects, &rules);
+    }
+
+    let changes = match config.get_change_source() {
+        Some(source) => Some(ChangeSet::load(
+            project_root,
+            source,
+            config.get_changed_lines_only(),
+        )?),
+        None => None,
+    };
+
+    let cache = match config.get_no_cache() {
+        true => None,
+        false => Some(Cache::load(project_root, &rules)?),
+    };
+    let mut report = lint(
+        config,
+        project_root,
+        &projects,
+        &rules,
+        changes.as_ref(),
+        cache.as_ref(),
+    )?;
+    if let Some(cache) = &cache {
+        cache.save(project_root)?;
+    }
+
+    if config.get_write_baseline() {
+        let baseline_path = match config.get_baseline_path() {
+            Some(path) => path.to_path_buf(),
+            None => Baseline::default_path(project_root),
+        };
+        let baseline = Baseline::from_diagnostics(project_root, &report.diagnostics());
+        baseline.save(&baseline_path)?;
+        eprintln!(
+            "apptester_lint: wrote {} baseline entr(ies) to {}",
+            baseline.get_entries().len(),
+            baseline_path.display()
+        );
+        return Ok(ExitStatus::Clean);
+    }
+    let baseline = load_baseline(config, project_root)?;
+    let mut fixed_entries = baseline.apply(&mut report, changes.as_ref());
+
+    match config.get_fix_mode() {
+        Some(FixMode::DryRun) => {
+            let fixed_files = fix_files(&report.diagnostics())?;
+            for fixed_file in &fixed_files {
+                print!("{}", fixed_file.diff(Path::new(project_root)));
+            }
+            return Ok(exit_status(config, &report));
+        }
+        Some(FixMode::Apply) => {
+            let fixed_files = fix_files(&report.diagnostics())?;
+            let (mut applied, mut skipped) = (0, 0);
+            for fixed_file in &fixed_files {
+                fixed_file.write()?;
+                applied += fixed_file.get_applied();
+                skipped += fixed_file.get_skipped();
+            }
+            eprintln!(
+                "apptester_lint: fixed {applied} problem(s) in {} file(s)",
+                fixed_files.len()
+            );
+            if skipped > 0 {
+                eprintln!(
+                    "apptester_lint: {skipped} fix(es) overlapped another fix, run --fix again to apply them"
+                );
+            }
+            // Report what's left after fixing
+            report = lint(
+                config,
+                project_root,
+                &projects,
+                &rules,
+                changes.as_ref(),
+                cache.as_ref(),
+            )?;
+            if let Some(cache) = &cache {
+                cache.save(project_root)?;
+            }
+            fixed_entries = baseline.apply(&mut report, changes.as_ref());
+        }
+        None => {}
+    }
+
+    let formatter = get_formatter(config.get_format());
+    match config.get_output_path() {
+        Some(output_path) => {
+            if config.get_format() == OutputFormat::Text {
+                colored::control::set_override(false);
+            }
+            fs::write(output_path, formatter.format(&report))?;
+        }
+        None => print!("{}", formatter.format(&report)),
+    }
 
-    for subdir in project.get_subdirs() {
-        process_subdir(subdir, &rules)?;
+    if !fixed_entries.is_empty() {
+        eprintln!(
+            "apptester_lint: {} baseline entr(ies) no longer found, run --write-baseline to remove them:",
+            fixed_entries.len()
+        );
+        for entry in fixed_entries {
+            eprintln!(
+                "  {}: {}: {}",
+                entry.get_path(),
+                entry.get_rule_id(),
+                entry.get_text()
+            );
+        }
     }
 
-    Ok(())
+    Ok(exit_status(config, &report))
+}
+
+/// Lints, then lints again and redraws a compact report whenever files change.
+fn run_watch(
+    config: &Config,
+    project_root: &str,
+    projects: &[Project],
+    rules: &Rules,
+) -> Result<ExitStatus> {
+    let cache = match config.get_no_cache() {
+        true => Cache::new(rules),
+        false => Cache::load(project_root, rules)?,
+    };
+    let baseline = load_baseline(config, project_root)?;
+    let mut report = lint(config, project_root, projects, rules, None, Some(&cache))?;
+
+    watch(projects, &mut report, rules, &cache, |report| {
+        if !config.get_no_cache() {
+            cache.save(project_root)?;
+        }
+        let mut report = report.clone();
+        baseline.apply(&mut report, None);
+        // Clear the terminal before each redraw
+        print!("\x1b[2J\x1b[H{}", CompactFormatter.format(&report));
+        io::stdout().flush()?;
+        Ok(())
+    })?;
+
+    Ok(exit_status(config, &report))
+}
+
+fn lint(
+    config: &Config,
+    project_root: &str,
+    projects: &[Project],
+    rules: &Rules,
+    changes: Option<&ChangeSet>,
+    cache: Option<&Cache>,
+) -> Result<Report> {
+    let mut report = Report::new(project_root, config.get_feature().is_none());
+    for feature_report in process_projects(projects, rules, changes, cache)? {
+        report.add_feature(feature_report);
+    }
+
+    Ok(report)
+}
+
+/// The baseline given with `--baseline`, or the project's default one if it
+/// has one.
+fn load_baseline(config: &Config, project_root: &str) -> Result<Baseline> {
+    let baseline_path = match config.get_baseline_path() {
+        Some(path) => path.to_path_buf(),
+        None => Baseline::default_path(project_root),
+    };
+    match Baseline::load(&baseline_path)? {
+        Some(baseline) => Ok(baseline),
+        None if config.get_baseline_path().is_some() => {
+            Err(format!("could not read baseline {}", baseline_path.display()).into())
+        }
+        None => Ok(Baseline::default()),
+    }
+}
+
+fn exit_status(config: &Config, report: &Report) -> ExitStatus {
+    ExitStatus::from_diagnostics(
+        &report.diagnostics(),
+        config.get_max_warnings(),
+        config.get_warnings_as_errors(),
+    )
 }
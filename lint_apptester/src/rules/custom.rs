@@ -0,0 +1,278 @@
+//! Rules declared in `lint_apptester.toml` as a regex that must not, or must,
+//! match part of each file.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+use crate::{
+    java::{Token, TokenKind},
+    lint_config::{CustomRuleConfig, LintConfig, RuleScope},
+    Diagnostic, DirType, LintRule, Result, RuleContext, Severity, SourceFile,
+};
+
+/// Built-in rules simple enough to be declared like custom rules. A custom rule
+/// with the same id replaces them, or any other rule, though a replaced rule's
+/// fixes are lost.
+const BUILT_IN_RULES: &str = r#"
+[[custom_rules]]
+id = "no-assert-calls"
+description = "No assert calls"
+message = "assertions do not belong in step definitions"
+dir_types = ["steps"]
+scope = "class_body"
+forbidden = '\bassert(?:[A-Z]\w*)?\b'
+"#;
+
+pub struct CustomRule {
+    config: CustomRuleConfig,
+    dir_types: Vec<DirType>,
+    globs: Option<GlobSet>,
+    pattern: Pattern,
+}
+
+enum Pattern {
+    Forbidden(Regex),
+    Required(Regex),
+}
+
+pub fn built_in_rules() -> Vec<CustomRule> {
+    LintConfig::parse(BUILT_IN_RULES)
+        .and_then(|config| {
+            config
+                .get_custom_rules()
+                .iter()
+                .map(CustomRule::new)
+                .collect()
+        })
+        .expect("built-in rules are valid")
+}
+
+impl CustomRule {
+    pub fn new(config: &CustomRuleConfig) -> Result<Self> {
+        let id = config.get_id();
+        let regex = |pattern: &str| {
+            Regex::new(pattern).map_err(|err| format!("invalid regex in custom rule '{id}': {err}"))
+        };
+        let pattern = match (config.get_forbidden(), config.get_required()) {
+            (Some(forbidden), None) => Pattern::Forbidden(regex(forbidden)?),
+            (None, Some(required)) => Pattern::Required(regex(required)?),
+            _ => {
+                return Err(format!(
+                    "custom rule '{id}' needs exactly one of forbidden and required"
+                )
+                .into())
+            }
+        };
+
+        let globs = match config.get_globs() {
+            [] => None,
+            globs => {
+                let mut builder = GlobSetBuilder::new();
+                for glob in globs {
+                    // Relative globs match the end of the path
+                    let glob = match glob.starts_with('/') || glob.starts_with("**") {
+                        true => glob.to_owned(),
+                        false => format!("**/{glob}"),
+                    };
+                    builder.add(
+                        Glob::new(&glob)
+                            .map_err(|err| format!("invalid glob in custom rule '{id}': {err}"))?,
+                    );
+                }
+                Some(builder.build()?)
+            }
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            dir_types: config
+                .get_dir_types()
+                .map_or(DirType::ALL.to_vec(), <[DirType]>::to_vec),
+            globs,
+            pattern,
+        })
+    }
+}
+
+impl LintRule for CustomRule {
+    fn id(&self) -> &str {
+        self.config.get_id()
+    }
+
+    fn description(&self) -> &str {
+        self.config.get_description()
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &self.dir_types
+    }
+
+    fn severity(&self) -> Severity {
+        self.config.get_severity().unwrap_or(Severity::Error)
+    }
+
+    fn is_enabled_by_default(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+        if self
+            .globs
+            .as_ref()
+            .is_some_and(|globs| !globs.is_match(file.get_path()))
+        {
+            return Vec::new();
+        }
+
+        let text = scoped_text(file, self.config.get_scope());
+        let message = self.config.get_message();
+        match &self.pattern {
+            Pattern::Forbidden(regex) => {
+                let line_starts: Vec<usize> = std::iter::once(0)
+                    .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+                    .collect();
+                regex
+                    .find_iter(&text)
+                    .map(|found| {
+                        let line = line_starts.partition_point(|&start| start <= found.start());
+                        let column = found.start() - line_starts[line - 1];
+                        file.diagnostic(self.id(), line, column, message)
+                    })
+                    .collect()
+            }
+            Pattern::Required(regex) if regex.is_match(&text) => Vec::new(),
+            Pattern::Required(_) => vec![file.diagnostic(self.id(), 1, 0, message)],
+        }
+    }
+}
+
+/// The file's text with everything outside `scope` blanked out, so matches
+/// keep their line and column. Comments and the contents of string literals
+/// are blanked in Java and JavaScript files; other files only have a file scope.
+fn scoped_text(file: &SourceFile, scope: RuleScope) -> String {
+    let lines: Vec<&str> = file.lines().map(|line| line.get_text()).collect();
+    if file.get_gherkin().is_some() {
+        return match scope {
+            RuleScope::File => lines.join("\n"),
+            RuleScope::Imports | RuleScope::ClassBody => String::new(),
+        };
+    }
+
+    let tokens: Vec<&Token> = match scope {
+        RuleScope::Imports => file.header_tokens().collect(),
+        RuleScope::ClassBody => file.class_tokens().collect(),
+        RuleScope::File => file.code_tokens().collect(),
+    };
+    let mut blanked: Vec<Vec<u8>> = lines.iter().map(|line| vec![b' '; line.len()]).collect();
+    for token in tokens {
+        let text = token.get_text();
+        // Only multi-line strings span lines, and they're blanked anyway
+        if text.contains('\n') {
+            continue;
+        }
+        let is_literal = matches!(
+            token.get_kind(),
+            TokenKind::StringLiteral | TokenKind::CharLiteral
+        );
+        let line = &mut blanked[token.get_line() - 1];
+        for (offset, byte) in text.bytes().enumerate() {
+            if !is_literal || offset == 0 || offset == text.len() - 1 {
+                line[token.get_column() + offset] = byte;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&blanked.join(&b'\n')).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::CustomRule;
+    use crate::{
+        lint_config::LintConfig, rules::get_rules, rules::tests::test_project, DirType, LintRule,
+        RuleContext, SourceFile,
+    };
+
+    const SOURCE: &str = "package a;\n\
+                          import a.b.Thread;\n\
+                          class Steps {\n    \
+                              // Thread.sleep(1)\n    \
+                              void a() { Thread.sleep(1); log(\"Thread.sleep(1)\"); }\n\
+                          }\n";
+
+    fn custom_rule(toml: &str) -> CustomRule {
+        let config = LintConfig::parse(&format!(
+            "[[custom_rules]]\nid = \"custom\"\nmessage = \"m\"\n{toml}"
+        ))
+        .unwrap();
+        CustomRule::new(&config.get_custom_rules()[0]).unwrap()
+    }
+
+    fn lines(rule: CustomRule, path: &str) -> Vec<(usize, usize)> {
+        let project = test_project();
+        let subdir = project.get_subdir(DirType::Steps).unwrap();
+        let file = SourceFile::from_contents(Path::new(path), DirType::Steps, SOURCE);
+        rule.check(&file, &RuleContext::new(&project, subdir))
+            .iter()
+            .map(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()))
+            .collect()
+    }
+
+    #[test]
+    fn custom_rules_match_code_in_their_scope() {
+        let forbidden = "forbidden = 'Thread\\.?'";
+        assert_eq!(
+            lines(custom_rule(forbidden), "Steps.java"),
+            vec![(2, 12), (5, 16)]
+        );
+        assert_eq!(
+            lines(
+                custom_rule(&format!("{forbidden}\nscope = \"imports\"")),
+                "Steps.java"
+            ),
+            vec![(2, 12)]
+        );
+        assert_eq!(
+            lines(
+                custom_rule(&format!("{forbidden}\nglobs = [\"pages/*.java\"]")),
+                "/steps/Steps.java"
+            ),
+            vec![]
+        );
+
+        let required = "required = 'LOGGER'\nscope = \"class_body\"";
+        assert_eq!(lines(custom_rule(required), "Steps.java"), vec![(1, 1)]);
+        assert!(
+            LintConfig::parse("[[custom_rules]]\nid = \"a\"\nmessage = \"b\"\n")
+                .and_then(|config| CustomRule::new(&config.get_custom_rules()[0]).map(|_| ()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn custom_rules_replace_built_in_rules_with_the_same_id() {
+        let mut rules = get_rules();
+        let rule_count = rules.get_rules().len();
+        rules
+            .configure(
+                LintConfig::parse(
+                    "[[custom_rules]]\nid = \"no-assert-calls\"\nmessage = \"use assertThat\"\nforbidden = 'assertTrue'\n\n\
+                     [[custom_rules]]\nid = \"no-thread-sleep\"\nmessage = \"m\"\nforbidden = 'Thread\\.sleep'\n\n\
+                     [rules.no-thread-sleep]\nseverity = \"info\"\n",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let ids: Vec<&str> = rules.get_rules().iter().map(|rule| rule.id()).collect();
+        assert_eq!(ids.len(), rule_count + 1);
+        assert_eq!(ids[1], "no-assert-calls");
+        assert_eq!(rules.get_rules()[1].description(), "use assertThat");
+        assert_eq!(
+            ids[ids.len() - 2..],
+            ["no-thread-sleep", "unused-suppression"]
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! Rules for Gherkin `.feature` files.
+
+use std::collections::HashMap;
+
+use crate::{
+    gherkin::{Scenario, StepKeyword},
+    lint_config::RuleConfig,
+    Diagnostic, DirType, LintRule, RuleContext, Severity, SourceFile,
+};
+
+const DEFAULT_MAX_SCENARIO_STEPS: usize = 10;
+
+pub struct FeatureNames;
+
+impl LintRule for FeatureNames {
+    fn id(&self) -> &str {
+        "feature-names"
+    }
+
+    fn description(&self) -> &str {
+        "Features and scenarios have names"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+        let Some(document) = file.get_gherkin() else {
+            return Vec::new();
+        };
+        let Some(feature) = document.get_feature() else {
+            return vec![file.diagnostic(self.id(), 1, 0, "file has no Feature")];
+        };
+
+        let mut diagnostics = Vec::new();
+        if feature.get_name().is_empty() {
+            diagnostics.push(file.diagnostic(
+                self.id(),
+                feature.get_line(),
+                feature.get_column(),
+                "Feature has no name",
+            ));
+        }
+        for scenario in feature.get_scenarios() {
+            if scenario.get_name().is_empty() {
+                diagnostics.push(file.diagnostic(
+                    self.id(),
+                    scenario.get_line(),
+                    scenario.get_column(),
+                    "Scenario has no name",
+                ));
+            }
+        }
+
+        diagnostics
+    }
+}
+
+pub struct NoEmptyScenarios;
+
+impl LintRule for NoEmptyScenarios {
+    fn id(&self) -> &str {
+        "no-empty-scenarios"
+    }
+
+    fn description(&self) -> &str {
+        "No empty scenarios"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+        scenarios(file)
+            .filter(|scenario| scenario.get_steps().is_empty())
+            .map(|scenario| {
+                file.diagnostic(
+                    self.id(),
+                    scenario.get_line(),
+                    scenario.get_column(),
+                    "Scenario has no steps",
+                )
+            })
+            .collect()
+    }
+}
+
+pub struct NoDuplicateScenarioNames;
+
+impl LintRule for NoDuplicateScenarioNames {
+    fn id(&self) -> &str {
+        "no-duplicate-scenario-names"
+    }
+
+    fn description(&self) -> &str {
+        "No duplicate scenario names"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+        let mut first_lines: HashMap<&str, usize> = HashMap::new();
+        let mut diagnostics = Vec::new();
+        for scenario in scenarios(file).filter(|scenario| !scenario.get_name().is_empty()) {
+            match first_lines.get(scenario.get_name()) {
+                Some(first_line) => diagnostics.push(file.diagnostic(
+                    self.id(),
+                    scenario.get_line(),
+                    scenario.get_column(),
+                    &format!("Scenario name is already used on line {first_line}"),
+                )),
+                None => {
+                    first_lines.insert(scenario.get_name(), scenario.get_line());
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+pub struct GivenWhenThenOrder;
+
+impl LintRule for GivenWhenThenOrder {
+    fn id(&self) -> &str {
+        "given-when-then-order"
+    }
+
+    fn description(&self) -> &str {
+        "Steps follow Given, When, Then order"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for scenario in scenarios(file) {
+            let mut previous: Option<StepKeyword> = None;
+            for step in scenario.get_steps() {
+                let keyword = step.get_keyword();
+                let message = match (previous, keyword.is_conjunction()) {
+                    (None, true) => Some("first step must be Given, When or Then"),
+                    (Some(previous), false) if order(keyword) < order(previous) => {
+                        Some("step is out of Given, When, Then order")
+                    }
+                    _ => None,
+                };
+                if let Some(message) = message {
+                    diagnostics.push(file.diagnostic(
+                        self.id(),
+                        step.get_line(),
+                        step.get_column(),
+                        message,
+                    ));
+                }
+
+                if !keyword.is_conjunction() {
+                    previous = Some(keyword);
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+pub struct MaxScenarioSteps;
+
+impl LintRule for MaxScenarioSteps {
+    fn id(&self) -> &str {
+        "max-scenario-steps"
+    }
+
+    fn description(&self) -> &str {
+        "Scenarios are not too long"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn options(&self) -> &[&str] {
+        &["max_steps"]
+    }
+
+    fn check_options(&self, rule_config: &RuleConfig) -> std::result::Result<(), String> {
+        match rule_config.get_option("max_steps") {
+            Some(value) => max_steps(value).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic> {
+        // An invalid option was already reported by check_options
+        let max_steps = context
+            .get_option("max_steps")
+            .and_then(|value| max_steps(value).ok())
+            .unwrap_or(DEFAULT_MAX_SCENARIO_STEPS);
+
+        scenarios(file)
+            .filter(|scenario| scenario.get_steps().len() > max_steps)
+            .map(|scenario| {
+                file.diagnostic(
+                    self.id(),
+                    scenario.get_line(),
+                    scenario.get_column(),
+                    &format!(
+                        "Scenario has {} steps, more than the maximum of {max_steps}",
+                        scenario.get_steps().len()
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+fn max_steps(value: &toml::Value) -> std::result::Result<usize, String> {
+    value
+        .as_integer()
+        .and_then(|value| usize::try_from(value).ok())
+        .filter(|&value| value > 0)
+        .ok_or_else(|| format!("option max_steps must be a positive integer, not {value}"))
+}
+
+pub struct FeatureWhitespace;
+
+impl LintRule for FeatureWhitespace {
+    fn id(&self) -> &str {
+        "feature-whitespace"
+    }
+
+    fn description(&self) -> &str {
+        "No trailing whitespace or tab indentation"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, file: &SourceFile, _context: &RuleContext) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for line in file.lines() {
+            let text = line.get_text();
+            let indent = &text[..text.len() - text.trim_start().len()];
+            if let Some(column) = indent.find('\t') {
+                diagnostics.push(file.diagnostic(
+                    self.id(),
+                    line.get_number(),
+                    column,
+                    "indent with spaces, not tabs",
+                ));
+            }
+
+            let content_end = text.trim_end().len();
+            if content_end < text.len() && !text.trim().is_empty() {
+                diagnostics.push(file.diagnostic(
+                    self.id(),
+                    line.get_number(),
+                    content_end,
+                    "trailing whitespace",
+                ));
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Scenarios of the file's feature, excluding the background.
+fn scenarios(file: &SourceFile) -> impl Iterator<Item = &Scenario> {
+    file.get_gherkin()
+        .and_then(|document| document.get_feature())
+        .into_iter()
+        .flat_map(|feature| feature.get_scenarios())
+}
+
+fn order(keyword: StepKeyword) -> u8 {
+    match keyword {
+        StepKeyword::Given => 0,
+        StepKeyword::When => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        FeatureNames, FeatureWhitespace, GivenWhenThenOrder, MaxScenarioSteps,
+        NoDuplicateScenarioNames, NoEmptyScenarios,
+    };
+    use crate::{
+        lint_config::RuleConfig, rules::tests::test_project, DirType, LintRule, RuleContext,
+        SourceFile,
+    };
+
+    fn check_feature(rule: impl LintRule, contents: &str) -> Vec<(usize, String)> {
+        let project = test_project();
+        let subdir = project.get_subdir(DirType::Features).unwrap();
+        let file = SourceFile::from_contents(Path::new("a.feature"), DirType::Features, contents);
+
+        rule.check(&file, &RuleContext::new(&project, subdir))
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.get_line(),
+                    String::from(diagnostic.get_message()),
+                )
+            })
+            .collect()
+    }
+
+    const FEATURE: &str = "Feature:\n\
+                           \n\
+                           \x20 Scenario: Open\n\
+                           \x20   And I start\n\
+                           \x20   Given I open files\n\
+                           \x20   When I tap\n\
+                           \x20   Given I open photos\n\
+                           \x20   Then I see photos \n\
+                           \n\
+                           \x20 Scenario:\n\
+                           \n\
+                           \x20 Scenario: Open\n\
+                           \t  Given I open files\n";
+
+    #[test]
+    fn feature_names_reports_missing_names() {
+        assert_eq!(
+            check_feature(FeatureNames, FEATURE),
+            vec![
+                (1, String::from("Feature has no name")),
+                (10, String::from("Scenario has no name")),
+            ]
+        );
+        assert_eq!(
+            check_feature(FeatureNames, "# nothing here\n"),
+            vec![(1, String::from("file has no Feature"))]
+        );
+    }
+
+    #[test]
+    fn scenario_rules_report_empty_and_duplicate_scenarios() {
+        assert_eq!(
+            check_feature(NoEmptyScenarios, FEATURE),
+            vec![(10, String::from("Scenario has no steps"))]
+        );
+        assert_eq!(
+            check_feature(NoDuplicateScenarioNames, FEATURE),
+            vec![(12, String::from("Scenario name is already used on line 3"))]
+        );
+    }
+
+    #[test]
+    fn given_when_then_order_reports_out_of_order_steps() {
+        assert_eq!(
+            check_feature(GivenWhenThenOrder, FEATURE),
+            vec![
+                (4, String::from("first step must be Given, When or Then")),
+                (7, String::from("step is out of Given, When, Then order")),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_scenario_steps_uses_default_limit() {
+        let long_scenario = format!("Feature: A\n  Scenario: B\n{}", "    Given a\n".repeat(11));
+
+        assert_eq!(
+            check_feature(MaxScenarioSteps, &long_scenario),
+            vec![(
+                2,
+                String::from("Scenario has 11 steps, more than the maximum of 10")
+            )]
+        );
+        assert!(check_feature(MaxScenarioSteps, FEATURE).is_empty());
+    }
+
+    #[test]
+    fn max_scenario_steps_rejects_non_positive_limits() {
+        let check_options = |rule_config: &str| {
+            MaxScenarioSteps.check_options(&toml::from_str::<RuleConfig>(rule_config).unwrap())
+        };
+
+        assert!(check_options("max_steps = 5").is_ok());
+        assert_eq!(
+            check_options("max_steps = -1").unwrap_err(),
+            "option max_steps must be a positive integer, not -1"
+        );
+        assert!(check_options("max_steps = 0").is_err());
+        assert!(check_options("max_steps = \"5\"").is_err());
+    }
+
+    #[test]
+    fn feature_whitespace_reports_tabs_and_trailing_spaces() {
+        assert_eq!(
+            check_feature(FeatureWhitespace, FEATURE),
+            vec![
+                (8, String::from("trailing whitespace")),
+                (13, String::from("indent with spaces, not tabs")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Dependency direction between dir types: steps use interactions, interactions
+//! use pages, and pages use neither.
+
+use std::path::{Component, Path};
+
+use crate::{
+    java::{Token, TokenKind},
+    lint_config::RuleConfig,
+    Diagnostic, DirType, LintRule, Project, RuleContext, SourceFile,
+};
+
+/// The directory packages start in when the `source_root` option isn't set.
+const DEFAULT_SOURCE_ROOT: &str = "java";
+
+/// The dir types each dir type may depend on, besides itself.
+const DEFAULT_ALLOWED: [(DirType, &[DirType]); 3] = [
+    (DirType::Steps, &[DirType::Interactions]),
+    (DirType::Interactions, &[DirType::Pages]),
+    (DirType::Pages, &[]),
+];
+
+pub struct Layering;
+
+impl LintRule for Layering {
+    fn id(&self) -> &str {
+        "layering"
+    }
+
+    fn description(&self) -> &str {
+        "Steps use interactions and interactions use pages"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Steps, DirType::Interactions, DirType::Pages]
+    }
+
+    fn options(&self) -> &[&str] {
+        &["allowed", "source_root"]
+    }
+
+    fn check_options(&self, rule_config: &RuleConfig) -> std::result::Result<(), String> {
+        let allowed = rule_config.get_option("allowed");
+        if let Some(table) = allowed.and_then(toml::Value::as_table) {
+            if let Some(key) = table.keys().find(|key| {
+                !self
+                    .dir_types()
+                    .iter()
+                    .any(|dir_type| dir_type.label() == *key)
+            }) {
+                return Err(format!("option allowed has unknown dir type '{key}'"));
+            }
+        }
+        for &dir_type in self.dir_types() {
+            configured_allowed(dir_type, allowed)?;
+        }
+        if let Some(source_root) = rule_config.get_option("source_root") {
+            if !source_root.as_str().is_some_and(|path| !path.is_empty()) {
+                return Err(String::from("option source_root must be a path"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_project(
+        &self,
+        project: &Project,
+        rule_config: Option<&RuleConfig>,
+    ) -> std::result::Result<(), String> {
+        let source_root =
+            source_root(rule_config.and_then(|config| config.get_option("source_root")));
+        let outside: Vec<String> = project
+            .get_dir_type_paths()
+            .iter()
+            .filter(|(dir_type, path)| {
+                self.dir_types().contains(dir_type) && package_root(path, source_root).is_none()
+            })
+            .map(|(_, path)| path.display().to_string())
+            .collect();
+
+        match outside.is_empty() {
+            true => Ok(()),
+            false => Err(format!(
+                "{} not in a package under the source root '{source_root}', set the source_root option to the directory packages start in",
+                outside.join(", ")
+            )),
+        }
+    }
+
+    fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic> {
+        let dir_type = file.get_dir_type();
+        // An invalid option was already reported by check_options
+        let allowed = configured_allowed(dir_type, context.get_option("allowed"))
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| default_allowed(dir_type));
+
+        let imports = file.get_imports();
+        let references = imports
+            .iter()
+            .map(|import| {
+                (
+                    import.get_path().to_owned(),
+                    import.get_line(),
+                    import.get_column(),
+                )
+            })
+            .chain(qualified_references(file));
+
+        let project = context.get_project();
+        let source_root = source_root(context.get_option("source_root"));
+        references
+            .filter_map(|(name, line, column)| {
+                let dependency = resolve_dir_type(project, source_root, &name)?;
+                if dependency == dir_type || allowed.contains(&dependency) {
+                    return None;
+                }
+                Some(file.diagnostic(
+                    self.id(),
+                    line,
+                    column,
+                    &format!(
+                        "{} may not depend on {}: {name}",
+                        dir_type.label(),
+                        dependency.label()
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+/// The dir types `dir_type` may depend on according to the `allowed` option,
+/// e.g. `allowed = { steps = ["interactions"] }`, if it sets them.
+fn configured_allowed(
+    dir_type: DirType,
+    allowed: Option<&toml::Value>,
+) -> std::result::Result<Option<Vec<DirType>>, String> {
+    let configured = match allowed {
+        Some(allowed) => allowed
+            .as_table()
+            .ok_or("option allowed must be a table of dir types")?
+            .get(dir_type.label()),
+        None => None,
+    };
+
+    configured
+        .map(|value| {
+            value
+                .clone()
+                .try_into()
+                .map_err(|err| format!("invalid option allowed.{}: {err}", dir_type.label()))
+        })
+        .transpose()
+}
+
+fn default_allowed(dir_type: DirType) -> Vec<DirType> {
+    DEFAULT_ALLOWED
+        .iter()
+        .find(|(from, _)| *from == dir_type)
+        .map_or(Vec::new(), |(_, allowed)| allowed.to_vec())
+}
+
+fn source_root(option: Option<&toml::Value>) -> &str {
+    option
+        .and_then(toml::Value::as_str)
+        .unwrap_or(DEFAULT_SOURCE_ROOT)
+}
+
+/// Which dir type a fully qualified name belongs to, found by matching its
+/// package against the package root of each dir type's path. `a.b.pages.files.FilesPage`
+/// belongs to a dir type at `src/test/java/a/b/pages/`, but `c.d.pages.Page` doesn't.
+pub fn resolve_dir_type(project: &Project, source_root: &str, name: &str) -> Option<DirType> {
+    let segments: Vec<&str> = name.split('.').collect();
+    let package = &segments[..segments.len().saturating_sub(1)];
+
+    project
+        .get_dir_type_paths()
+        .iter()
+        .filter_map(|(dir_type, path)| {
+            let root = package_root(path, source_root)?;
+            package
+                .starts_with(&root)
+                .then_some((root.len(), *dir_type))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, dir_type)| dir_type)
+}
+
+/// The package of the classes in `path`: its components after the last
+/// `source_root`, e.g. `a.b.pages` for `src/test/java/a/b/pages/` and `java`.
+fn package_root<'a>(path: &'a Path, source_root: &str) -> Option<Vec<&'a str>> {
+    let path = components(path);
+    let source_root = components(Path::new(source_root));
+    if source_root.is_empty() {
+        return None;
+    }
+    let start = path
+        .windows(source_root.len())
+        .rposition(|window| window == source_root.as_slice())?;
+    let root = path[start + source_root.len()..].to_vec();
+
+    (!root.is_empty()).then_some(root)
+}
+
+fn components(path: &Path) -> Vec<&str> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Fully qualified class names used in code, e.g. `a.b.pages.FilesPage.open()`,
+/// with the line and column they start at.
+fn qualified_references(file: &SourceFile) -> Vec<(String, usize, usize)> {
+    let tokens: Vec<&Token> = file.class_tokens().collect();
+    let mut references = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        let follows_dot = index > 0 && tokens[index - 1].is_punctuation(".");
+        if follows_dot || !is_package_segment(token) {
+            continue;
+        }
+
+        let mut segments = vec![token.get_text()];
+        let mut next = index + 1;
+        while next + 1 < tokens.len()
+            && tokens[next].is_punctuation(".")
+            && tokens[next + 1].get_kind() == TokenKind::Identifier
+        {
+            let segment = tokens[next + 1].get_text();
+            segments.push(segment);
+            next += 2;
+            if !is_package_segment(tokens[next - 1]) {
+                break;
+            }
+        }
+
+        // Needs a package of at least two segments followed by a class name, so
+        // `files.openFiles()` or `this.page.Name` aren't mistaken for one
+        let is_class = segments
+            .last()
+            .is_some_and(|segment| segment.starts_with(char::is_uppercase));
+        if is_class && segments.len() > 2 {
+            references.push((segments.join("."), token.get_line(), token.get_column()));
+        }
+    }
+
+    references
+}
+
+fn is_package_segment(token: &Token) -> bool {
+    token.get_kind() == TokenKind::Identifier && token.get_text().starts_with(char::is_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use super::{resolve_dir_type, Layering};
+    use crate::{
+        lint_config::RuleConfig, DirType, LintRule, Project, RuleContext, SourceFile, Subdir,
+    };
+
+    fn test_project(package_path: &str, subdir_types: &[DirType]) -> Project {
+        let dir_type_path = |dir_type: DirType| Path::new(package_path).join(dir_type.label());
+        let subdirs = subdir_types
+            .iter()
+            .map(|&subdir_type| Subdir {
+                path: dir_type_path(subdir_type).join("files").into_boxed_path(),
+                subdir_type,
+            })
+            .collect();
+        let dir_type_paths = DirType::ALL
+            .into_iter()
+            .map(|dir_type| (dir_type, dir_type_path(dir_type)))
+            .collect();
+
+        Project::from_subdirs(
+            String::from("files"),
+            subdirs,
+            dir_type_paths,
+            Arc::default(),
+        )
+    }
+
+    fn check(dir_type: DirType, contents: &str, rule_config: Option<&str>) -> Vec<String> {
+        let project = test_project("/repo/src/test/java/a/b", &DirType::ALL);
+        let subdir = project.get_subdir(dir_type).unwrap();
+        let file = SourceFile::from_contents(Path::new("Test.java"), dir_type, contents);
+        let rule_config: Option<RuleConfig> =
+            rule_config.map(|rule_config| toml::from_str(rule_config).unwrap());
+
+        Layering
+            .check(
+                &file,
+                &RuleContext::new(&project, subdir).with_rule_config(rule_config.as_ref()),
+            )
+            .iter()
+            .map(|diagnostic| String::from(diagnostic.get_message()))
+            .collect()
+    }
+
+    #[test]
+    fn resolve_dir_type_matches_package_to_paths() {
+        let project = test_project("/repo/src/test/java/a/b", &DirType::ALL);
+
+        assert_eq!(
+            resolve_dir_type(&project, "java", "a.b.pages.photos.PhotosPage"),
+            Some(DirType::Pages)
+        );
+        assert_eq!(
+            resolve_dir_type(&project, "java", "a.b.interactions.*"),
+            Some(DirType::Interactions)
+        );
+        assert_eq!(resolve_dir_type(&project, "java", "java.util.List"), None);
+        assert_eq!(
+            resolve_dir_type(&project, "java", "c.d.pages.OtherPage"),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_dir_type_finds_dir_types_the_feature_lacks() {
+        let project = test_project("/repo/src/test/java/a/b", &[DirType::Steps]);
+
+        assert_eq!(
+            resolve_dir_type(&project, "java", "a.b.pages.files.FilesPage"),
+            Some(DirType::Pages)
+        );
+    }
+
+    #[test]
+    fn layering_reports_imports_and_qualified_names() {
+        let steps = "import a.b.interactions.files.FilesInteraction;\n\
+                     import a.b.pages.files.FilesPage;\n\
+                     import java.util.List;\n\
+                     class FilesSteps {\n\
+                     \x20   void open() {\n\
+                     \x20       files.openFiles();\n\
+                     \x20       a.b.pages.files.dialogs.DeleteDialog.confirm();\n\
+                     \x20   }\n\
+                     }\n";
+
+        assert_eq!(
+            check(DirType::Steps, steps, None),
+            vec![
+                String::from("steps may not depend on pages: a.b.pages.files.FilesPage"),
+                String::from("steps may not depend on pages: a.b.pages.files.dialogs.DeleteDialog"),
+            ]
+        );
+        assert_eq!(
+            check(
+                DirType::Pages,
+                "import a.b.steps.files.FilesSteps;\nclass FilesPage {}\n",
+                None
+            ),
+            vec![String::from(
+                "pages may not depend on steps: a.b.steps.files.FilesSteps"
+            )]
+        );
+    }
+
+    #[test]
+    fn layering_uses_configured_edges() {
+        let steps = "import a.b.pages.files.FilesPage;\n\
+                     import a.b.interactions.files.FilesInteraction;\n\
+                     import c.d.pages.OtherPage;\n\
+                     class FilesSteps {}\n";
+
+        assert!(check(
+            DirType::Steps,
+            steps,
+            Some("allowed = { steps = [\"interactions\", \"pages\"] }")
+        )
+        .is_empty());
+        assert_eq!(
+            check(
+                DirType::Steps,
+                steps,
+                Some("allowed = { steps = [\"pages\"] }")
+            ),
+            vec![String::from(
+                "steps may not depend on interactions: a.b.interactions.files.FilesInteraction"
+            )]
+        );
+    }
+
+    #[test]
+    fn layering_checks_allowed_option() {
+        let check_options = |rule_config: &str| {
+            Layering.check_options(&toml::from_str::<RuleConfig>(rule_config).unwrap())
+        };
+
+        assert!(check_options("allowed = { steps = [\"pages\"], pages = [] }").is_ok());
+        assert_eq!(
+            check_options("allowed = [\"pages\"]").unwrap_err(),
+            "option allowed must be a table of dir types"
+        );
+        assert!(check_options("allowed = { steps = [\"page\"] }")
+            .unwrap_err()
+            .starts_with("invalid option allowed.steps: "));
+        assert_eq!(
+            check_options("allowed = { step = [] }").unwrap_err(),
+            "option allowed has unknown dir type 'step'"
+        );
+    }
+
+    #[test]
+    fn layering_uses_configured_source_root() {
+        let project = test_project("/repo/test/js/a/b", &DirType::ALL);
+        let rule_config: RuleConfig = toml::from_str("source_root = \"test/js\"").unwrap();
+
+        assert_eq!(
+            resolve_dir_type(&project, "test/js", "a.b.pages.files.FilesPage"),
+            Some(DirType::Pages)
+        );
+        assert!(Layering.check_project(&project, Some(&rule_config)).is_ok());
+        assert_eq!(
+            Layering.check_project(&project, None).unwrap_err(),
+            "/repo/test/js/a/b/interactions, /repo/test/js/a/b/pages, /repo/test/js/a/b/steps not in a package under the source root 'java', set the source_root option to the directory packages start in"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Rules that cross reference feature steps with step definitions.
+
+use crate::{
+    cucumber::parse_step_definitions, Diagnostic, DirType, LintRule, RuleContext, Severity,
+    SourceFile,
+};
+
+pub struct UndefinedSteps;
+
+impl LintRule for UndefinedSteps {
+    fn id(&self) -> &str {
+        "undefined-steps"
+    }
+
+    fn description(&self) -> &str {
+        "Steps have exactly one definition"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Features]
+    }
+
+    fn is_cross_file(&self) -> bool {
+        true
+    }
+
+    fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic> {
+        let Some(feature) = file
+            .get_gherkin()
+            .and_then(|document| document.get_feature())
+        else {
+            return Vec::new();
+        };
+        let step_index = context.get_project().get_step_index();
+
+        let mut diagnostics = Vec::new();
+        for scenario in feature
+            .get_background()
+            .into_iter()
+            .chain(feature.get_scenarios())
+        {
+            for step in scenario.get_steps() {
+                let message = scenario.expand_step(step).iter().find_map(|step_text| {
+                    let definitions = step_index.matching_definitions(step_text);
+                    match definitions.len() {
+                        0 => Some(format!("undefined step '{step_text}'")),
+                        1 => None,
+                        _ => {
+                            let locations: Vec<String> = definitions
+                                .iter()
+                                .map(|definition| {
+                                    format!(
+                                        "{}:{}",
+                                        definition.get_path().display(),
+                                        definition.get_line()
+                                    )
+                                })
+                                .collect();
+                            Some(format!(
+                                "step '{step_text}' matches {} definitions: {}",
+                                definitions.len(),
+                                locations.join(", ")
+                            ))
+                        }
+                    }
+                });
+
+                if let Some(message) = message {
+                    diagnostics.push(file.diagnostic(
+                        self.id(),
+                        step.get_line(),
+                        step.get_column(),
+                        &message,
+                    ));
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+pub struct UnusedStepDefinitions;
+
+impl LintRule for UnusedStepDefinitions {
+    fn id(&self) -> &str {
+        "unused-step-definitions"
+    }
+
+    fn description(&self) -> &str {
+        "Step definitions are used by a feature"
+    }
+
+    fn dir_types(&self) -> &[DirType] {
+        &[DirType::Steps]
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn is_cross_file(&self) -> bool {
+        true
+    }
+
+    fn check(&self, file: &SourceFile, context: &RuleContext) -> Vec<Diagnostic> {
+        let step_index = context.get_project().get_step_index();
+
+        parse_step_definitions(file)
+            .iter()
+            .filter_map(|definition| {
+                let message = match definition.get_pattern() {
+                    Err(err) => format!("step definition is not a valid expression: {err}"),
+                    Ok(_) if !step_index.is_used(definition) => format!(
+                        "step definition '{}' is not used by any feature",
+                        definition.get_expression()
+                    ),
+                    Ok(_) => return None,
+                };
+                Some(file.diagnostic(
+                    self.id(),
+                    definition.get_line(),
+                    definition.get_column(),
+                    &message,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{UndefinedSteps, UnusedStepDefinitions};
+    use crate::{
+        cucumber::StepIndex, rules::tests::test_project, DirType, LintRule, RuleContext, SourceFile,
+    };
+
+    const STEPS: &str = "public class FilesSteps {\n\
+                         \x20   @Given(\"I open the files screen\")\n\
+                         \x20   public void open() {}\n\
+                         \n\
+                         \x20   @Then(\"I see {int} file(s)\")\n\
+                         \x20   public void see(int count) {}\n\
+                         \n\
+                         \x20   @Then(\"^I see (\\\\d+) files?$\")\n\
+                         \x20   public void seeAgain(int count) {}\n\
+                         \n\
+                         \x20   @When(\"I delete everything\")\n\
+                         \x20   public void delete() {}\n\
+                         }\n";
+
+    const FEATURE: &str = "Feature: Files\n\
+                           \x20 Background:\n\
+                           \x20   Given I open the files screen\n\
+                           \n\
+                           \x20 Scenario Outline: Count\n\
+                           \x20   When I tap <button>\n\
+                           \x20   Then I see <n> files\n\
+                           \x20   Examples:\n\
+                           \x20     | button | n |\n\
+                           \x20     | OK     | 2 |\n";
+
+    fn check(rule: impl LintRule, dir_type: DirType) -> Vec<(usize, String)> {
+        let steps = SourceFile::from_contents(Path::new("FilesSteps.java"), DirType::Steps, STEPS);
+        let feature =
+            SourceFile::from_contents(Path::new("files.feature"), DirType::Features, FEATURE);
+        let mut step_index = StepIndex::default();
+        step_index.add_file(&steps);
+        step_index.add_file(&feature);
+
+        let project = test_project();
+        project.step_index.set(step_index);
+        let subdir = project.get_subdir(dir_type).unwrap();
+        let file = if dir_type == DirType::Steps {
+            steps
+        } else {
+            feature
+        };
+
+        rule.check(&file, &RuleContext::new(&project, subdir))
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.get_line(),
+                    String::from(diagnostic.get_message()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn undefined_steps_reports_undefined_and_ambiguous_steps() {
+        assert_eq!(
+            check(UndefinedSteps, DirType::Features),
+            vec![
+                (6, String::from("undefined step 'I tap OK'")),
+                (
+                    7,
+                    String::from(
+                        "step 'I see 2 files' matches 2 definitions: FilesSteps.java:5, FilesSteps.java:8"
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_step_definitions_reports_definitions_without_steps() {
+        assert_eq!(
+            check(UnusedStepDefinitions, DirType::Steps),
+            vec![(
+                11,
+                String::from("step definition 'I delete everything' is not used by any feature")
+            )]
+        );
+    }
+}
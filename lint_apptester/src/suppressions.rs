@@ -0,0 +1,324 @@
+use crate::{Diagnostic, SourceFile};
+
+/// Marks the start of a directive inside a comment, e.g.
+/// `// lint-apptester-disable-next-line log-instead-of-sout`.
+pub const DIRECTIVE_PREFIX: &str = "lint-apptester-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirectiveKind {
+    /// `disable-next-line`
+    NextLine,
+    /// `disable-line`
+    Line,
+    /// `disable`, until a matching `enable` or the end of the file
+    Disable,
+    /// `enable`
+    Enable,
+    /// `disable-file`
+    File,
+}
+
+#[derive(Debug)]
+struct Directive {
+    kind: DirectiveKind,
+    rule_ids: Vec<String>,
+    line: usize,
+    column: usize,
+    text: String,
+}
+
+/// One rule silenced over a range of lines by a directive.
+#[derive(Debug)]
+struct Suppression {
+    rule_id: String,
+    first_line: usize,
+    last_line: usize,
+    directive: usize,
+    used: bool,
+}
+
+/// Suppression comments found in a single file.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    directives: Vec<Directive>,
+    suppressions: Vec<Suppression>,
+    invalid_directives: Vec<usize>,
+}
+
+impl Suppressions {
+    pub fn parse(file: &SourceFile) -> Self {
+        let mut directives = Vec::new();
+        let mut invalid_directives = Vec::new();
+        for (line, column, directive) in directive_comments(file) {
+            let directive = directive.trim_end().trim_end_matches("*/").trim();
+            let (kind, rule_ids) = directive
+                .split_once(char::is_whitespace)
+                .unwrap_or((directive, ""));
+            let kind = match kind {
+                "disable-next-line" => DirectiveKind::NextLine,
+                "disable-line" => DirectiveKind::Line,
+                "disable" => DirectiveKind::Disable,
+                "enable" => DirectiveKind::Enable,
+                "disable-file" => DirectiveKind::File,
+                _ => continue,
+            };
+            let rule_ids: Vec<String> = rule_ids
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|rule_id| !rule_id.is_empty())
+                .map(String::from)
+                .collect();
+
+            if rule_ids.is_empty() {
+                invalid_directives.push(directives.len());
+            }
+            directives.push(Directive {
+                kind,
+                rule_ids,
+                line,
+                column,
+                text: file
+                    .lines()
+                    .nth(line - 1)
+                    .map_or_else(String::new, |line| String::from(line.trimmed())),
+            });
+        }
+
+        let line_count = file.lines().count();
+        let mut suppressions = Vec::new();
+        for (index, directive) in directives.iter().enumerate() {
+            for rule_id in &directive.rule_ids {
+                let (first_line, last_line) = match directive.kind {
+                    DirectiveKind::NextLine => (directive.line + 1, directive.line + 1),
+                    DirectiveKind::Line => (directive.line, directive.line),
+                    DirectiveKind::File => (1, line_count),
+                    DirectiveKind::Disable => {
+                        let enable_line = directives[index + 1..]
+                            .iter()
+                            .find(|later| {
+                                later.kind == DirectiveKind::Enable
+                                    && later.rule_ids.contains(rule_id)
+                            })
+                            .map_or(line_count, |later| later.line);
+                        (directive.line, enable_line)
+                    }
+                    DirectiveKind::Enable => continue,
+                };
+                suppressions.push(Suppression {
+                    rule_id: rule_id.clone(),
+                    first_line,
+                    last_line,
+                    directive: index,
+                    used: false,
+                });
+            }
+        }
+
+        Self {
+            directives,
+            suppressions,
+            invalid_directives,
+        }
+    }
+
+    /// Whether `diagnostic` is silenced by a directive, recording that the
+    /// directive was needed.
+    pub fn suppresses(&mut self, diagnostic: &Diagnostic) -> bool {
+        let mut suppressed = false;
+        for suppression in &mut self.suppressions {
+            if suppression.rule_id == diagnostic.get_rule_id()
+                && (suppression.first_line..=suppression.last_line).contains(&diagnostic.get_line())
+            {
+                suppression.used = true;
+                suppressed = true;
+            }
+        }
+        suppressed
+    }
+
+    /// Diagnostics for directives that suppressed nothing. `rule_ran` says whether a
+    /// rule id was checked against this file, and `rule_exists` whether it is a
+    /// known rule at all; directives for rules that exist but did not run are
+    /// left alone.
+    pub fn unused(
+        &self,
+        rule_id: &str,
+        file: &SourceFile,
+        rule_ran: impl Fn(&str) -> bool,
+        rule_exists: impl Fn(&str) -> bool,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for &index in &self.invalid_directives {
+            let directive = &self.directives[index];
+            diagnostics.push(Diagnostic::new(
+                rule_id,
+                file.get_path(),
+                directive.line,
+                directive.column + 1,
+                &directive.text,
+                "suppression comment must name a rule id",
+            ));
+        }
+
+        for suppression in &self.suppressions {
+            let message = if !rule_exists(&suppression.rule_id) {
+                format!("suppression names unknown rule '{}'", suppression.rule_id)
+            } else if rule_ran(&suppression.rule_id) && !suppression.used {
+                format!("unused suppression for rule '{}'", suppression.rule_id)
+            } else {
+                continue;
+            };
+
+            let directive = &self.directives[suppression.directive];
+            diagnostics.push(Diagnostic::new(
+                rule_id,
+                file.get_path(),
+                directive.line,
+                directive.column + 1,
+                &directive.text,
+                &message,
+            ));
+        }
+
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()));
+        diagnostics
+    }
+}
+
+/// The directives in a file's comments, as the line and column of their
+/// prefix and the rest of that line. Code files need their tokens, so text that
+/// only looks like a directive, e.g. in a string literal, isn't read as one.
+fn directive_comments(file: &SourceFile) -> Vec<(usize, usize, &str)> {
+    if file
+        .get_path()
+        .extension()
+        .is_some_and(|extension| extension == "feature")
+    {
+        // Gherkin comments are whole lines starting with `#`
+        return file
+            .lines()
+            .filter_map(|line| {
+                let comment = line.trimmed().strip_prefix('#')?;
+                let directive = comment.trim_start().strip_prefix(DIRECTIVE_PREFIX)?;
+                let column = line.get_text().len() - directive.len() - DIRECTIVE_PREFIX.len();
+                Some((line.get_number(), column, directive))
+            })
+            .collect();
+    }
+
+    file.get_tokens()
+        .iter()
+        .filter(|token| token.get_kind().is_comment())
+        .filter_map(|token| {
+            let text = token.get_text();
+            let comment = ["//", "/**", "/*"]
+                .iter()
+                .find_map(|marker| text.strip_prefix(marker))?;
+            let comment = comment.trim_start();
+            let directive = comment.strip_prefix(DIRECTIVE_PREFIX)?;
+            // Only the first line of a block comment
+            let directive = directive.lines().next().unwrap_or_default();
+            let column = token.get_column() + text.len() - comment.len();
+            Some((token.get_line(), column, directive))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Suppressions;
+    use crate::{Diagnostic, DirType, SourceFile};
+
+    fn diagnostic(file: &SourceFile, rule_id: &str, line: usize) -> Diagnostic {
+        Diagnostic::new(rule_id, file.get_path(), line, 1, "", "")
+    }
+
+    #[test]
+    fn directives_suppress_their_lines() {
+        let file = SourceFile::from_contents(
+            Path::new("A.java"),
+            DirType::Steps,
+            "public class A {\n\
+             // lint-apptester-disable-next-line sout\n\
+             System.out.println(1);\n\
+             System.out.println(2); // lint-apptester-disable-line sout, assert\n\
+             /* lint-apptester-disable sout */\n\
+             System.out.println(3);\n\
+             // lint-apptester-enable sout\n\
+             System.out.println(4);\n\
+             }\n",
+        );
+        let mut suppressions = Suppressions::parse(&file);
+
+        let suppressed: Vec<usize> = (1..=9)
+            .filter(|&line| suppressions.suppresses(&diagnostic(&file, "sout", line)))
+            .collect();
+
+        assert_eq!(suppressed, vec![3, 4, 5, 6, 7]);
+        assert!(!suppressions.suppresses(&diagnostic(&file, "other", 3)));
+    }
+
+    #[test]
+    fn unused_reports_unneeded_and_unknown_directives() {
+        let file = SourceFile::from_contents(
+            Path::new("a.feature"),
+            DirType::Features,
+            "# lint-apptester-disable-file used\n\
+             # lint-apptester-disable-file unneeded, skipped, unknown\n\
+             # lint-apptester-disable-line\n\
+             Feature: A\n",
+        );
+        let mut suppressions = Suppressions::parse(&file);
+        assert!(suppressions.suppresses(&diagnostic(&file, "used", 4)));
+
+        let unused = suppressions.unused(
+            "unused-suppression",
+            &file,
+            |rule_id| rule_id != "skipped",
+            |rule_id| rule_id != "unknown",
+        );
+        let messages: Vec<&str> = unused.iter().map(|d| d.get_message()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "unused suppression for rule 'unneeded'",
+                "suppression names unknown rule 'unknown'",
+                "suppression comment must name a rule id",
+            ]
+        );
+    }
+
+    #[test]
+    fn directives_are_only_read_from_comments() {
+        let file = SourceFile::from_contents(
+            Path::new("A.java"),
+            DirType::Steps,
+            "public class A {\n\
+             String a = \"// lint-apptester-disable-next-line sout\";\n\
+             System.out.println(1);\n\
+             \x20   /** lint-apptester-disable-line sout */ System.out.println(2);\n\
+             }\n",
+        );
+        let mut suppressions = Suppressions::parse(&file);
+
+        assert!(!suppressions.suppresses(&diagnostic(&file, "sout", 3)));
+        assert!(suppressions.suppresses(&diagnostic(&file, "sout", 4)));
+        assert_eq!(suppressions.directives[0].column, 8);
+
+        let file = SourceFile::from_contents(
+            Path::new("a.feature"),
+            DirType::Features,
+            "Feature: A\n\
+             \x20 # lint-apptester-disable-next-line used\n\
+             \x20 Scenario: B\n\
+             \x20   Given a # lint-apptester-disable-line used\n",
+        );
+        let mut suppressions = Suppressions::parse(&file);
+
+        assert!(suppressions.suppresses(&diagnostic(&file, "used", 3)));
+        assert!(!suppressions.suppresses(&diagnostic(&file, "used", 4)));
+        assert_eq!(suppressions.directives[0].column, 4);
+    }
+}
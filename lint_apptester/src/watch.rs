@@ -0,0 +1,295 @@
+//! `--watch`: lints the affected subdirs again whenever files change.
+
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use rayon::prelude::*;
+
+use crate::{
+    cache::Cache, cucumber::SharedStepIndex, process_subdir, FeatureReport, LintRule, Project,
+    Report, Result, Rules, SubdirReport,
+};
+
+/// How long to wait after a change for more, so an IDE saving several files
+/// at once only causes one lint.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Calls `redraw` with `report`, then lints again and redraws whenever files in
+/// the subdirs of `projects` or the step index change, until the watcher stops.
+pub fn watch(
+    projects: &[Project],
+    report: &mut Report,
+    rules: &Rules,
+    cache: &Cache,
+    mut redraw: impl FnMut(&Report) -> Result<()>,
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for project in projects.iter() {
+        for subdir in &project.subdirs {
+            watcher.watch(subdir.get_path(), RecursiveMode::Recursive)?;
+        }
+    }
+    // Every project shares the step index, which can read files for other features
+    let step_index = projects.first().map(Project::get_shared_step_index);
+    for dir in step_index.map_or(&[][..], SharedStepIndex::get_dirs) {
+        watcher.watch(dir.get_path(), RecursiveMode::Recursive)?;
+    }
+
+    redraw(report)?;
+    while let Some(changed) = next_changes(&receiver, DEBOUNCE) {
+        let step_index_changed =
+            step_index.is_some_and(|index| step_index_changed(index, &changed));
+        if let Some(step_index) = step_index.filter(|_| step_index_changed) {
+            step_index.reset();
+            projects[0].prepare_step_index(rules);
+        }
+
+        let mut linted = false;
+        for (project, feature_report) in projects.iter().zip(&mut report.features) {
+            linted |= relint(
+                project,
+                feature_report,
+                rules,
+                &changed,
+                step_index_changed,
+                cache,
+            )?;
+        }
+        if linted {
+            redraw(report)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits for a change, then until `debounce` passes without another, and
+/// returns every path changed. Returns `None` once the watcher has stopped.
+pub fn next_changes(
+    receiver: &Receiver<notify::Result<Event>>,
+    debounce: Duration,
+) -> Option<BTreeSet<PathBuf>> {
+    let mut changed = BTreeSet::new();
+    let mut add = |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if !matches!(event.kind, EventKind::Access(_)) {
+                changed.extend(event.paths);
+            }
+        }
+    };
+
+    add(receiver.recv().ok()?);
+    while let Ok(event) = receiver.recv_timeout(debounce) {
+        add(event);
+    }
+    Some(changed)
+}
+
+/// Whether `changed` touches a file the shared step index was read from.
+pub fn step_index_changed(step_index: &SharedStepIndex, changed: &BTreeSet<PathBuf>) -> bool {
+    step_index
+        .get_dirs()
+        .iter()
+        .any(|dir| contains_change(dir.get_path(), changed))
+}
+
+/// Lints the subdirs of `project` affected by `changed` again and replaces
+/// their reports in `report`. Unchanged files are replayed from `cache`, so
+/// only changed files and, if the step index was reset, cross-file rules are
+/// checked again. Returns whether any subdir was affected.
+pub fn relint(
+    project: &Project,
+    report: &mut FeatureReport,
+    rules: &Rules,
+    changed: &BTreeSet<PathBuf>,
+    step_index_changed: bool,
+    cache: &Cache,
+) -> Result<bool> {
+    let affected = affected_subdirs(project, rules, changed, step_index_changed);
+    let subdir_reports = affected
+        .par_iter()
+        .map(|&index| {
+            process_subdir(project, &project.subdirs[index], rules, None, Some(cache))
+                .map_err(|err| err.to_string())
+        })
+        .collect::<std::result::Result<Vec<SubdirReport>, String>>()?;
+    for (&index, subdir_report) in affected.iter().zip(subdir_reports) {
+        report.subdirs[index] = subdir_report;
+    }
+
+    Ok(!affected.is_empty())
+}
+
+/// Indexes of the subdirs containing a changed file and, if the step index
+/// changed, of the subdirs checked by a cross-file rule.
+fn affected_subdirs(
+    project: &Project,
+    rules: &Rules,
+    changed: &BTreeSet<PathBuf>,
+    step_index_changed: bool,
+) -> Vec<usize> {
+    let cross_file_rules: Vec<&dyn LintRule> = rules
+        .get_rules()
+        .iter()
+        .map(|rule| rule.as_ref())
+        .filter(|&rule| rule.is_cross_file() && rules.is_enabled(rule))
+        .collect();
+
+    project
+        .subdirs
+        .iter()
+        .enumerate()
+        .filter(|(_, subdir)| {
+            contains_change(subdir.get_path(), changed)
+                || step_index_changed
+                    && cross_file_rules
+                        .iter()
+                        .any(|&rule| rules.get_dir_types(rule).contains(subdir.get_subdir_type()))
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn contains_change(path: &std::path::Path, changed: &BTreeSet<PathBuf>) -> bool {
+    changed
+        .iter()
+        .any(|changed_path| changed_path.starts_with(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        fs,
+        path::PathBuf,
+        sync::{mpsc, Arc},
+        time::Duration,
+    };
+
+    use notify::{event::CreateKind, Event, EventKind};
+
+    use super::{affected_subdirs, next_changes, relint, step_index_changed};
+    use crate::{
+        cache::Cache,
+        cucumber::{SharedStepIndex, StepIndex},
+        process_project,
+        rules::get_rules,
+        DirType, FeatureReport, Project, Subdir,
+    };
+
+    fn undefined_steps(report: &FeatureReport) -> usize {
+        report
+            .get_subdirs()
+            .iter()
+            .flat_map(|subdir| subdir.get_rules())
+            .filter(|rule| rule.get_id() == "undefined-steps")
+            .map(|rule| rule.get_diagnostics().len())
+            .sum()
+    }
+
+    #[test]
+    fn relint_checks_changed_subdirs_and_cross_file_rules() {
+        let root =
+            std::env::temp_dir().join(format!("lint_apptester_{}_watch", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let subdirs: Vec<Subdir> = DirType::ALL
+            .into_iter()
+            .map(|dir_type| {
+                let path = root.join(dir_type.label());
+                fs::create_dir_all(&path).unwrap();
+                Subdir {
+                    path: path.into_boxed_path(),
+                    subdir_type: dir_type,
+                }
+            })
+            .collect();
+        fs::write(
+            root.join("features/files.feature"),
+            "Feature: Files\n  Scenario: Open\n    Given I open files\n",
+        )
+        .unwrap();
+        let step_index_dirs = StepIndex::DIR_TYPES
+            .into_iter()
+            .map(|dir_type| Subdir {
+                path: root.join(dir_type.label()).into_boxed_path(),
+                subdir_type: dir_type,
+            })
+            .collect();
+        let project = Project {
+            feature_being_tested: "files".to_owned(),
+            follow_symlinks: false,
+            subdirs,
+            dir_type_paths: Vec::new(),
+            step_index: Arc::new(SharedStepIndex::new(step_index_dirs, false)),
+        };
+        let rules = get_rules();
+        let cache = Cache::load(root.to_str().unwrap(), &rules).unwrap();
+        let mut report = process_project(&project, &rules, None, Some(&cache)).unwrap();
+        assert_eq!(undefined_steps(&report), 1);
+
+        let page = root.join("pages/FilesPage.java");
+        fs::write(&page, "class FilesPage {}\n").unwrap();
+        let changed = BTreeSet::from([page]);
+        assert_eq!(affected_subdirs(&project, &rules, &changed, false), vec![2]);
+
+        // A new step definition changes which feature steps are undefined
+        let steps = root.join("steps/FilesSteps.java");
+        fs::write(
+            &steps,
+            "class FilesSteps {\n    @Given(\"I open files\")\n    void open() {}\n}\n",
+        )
+        .unwrap();
+        let changed = BTreeSet::from([steps]);
+        assert!(step_index_changed(
+            project.get_shared_step_index(),
+            &changed
+        ));
+        project.reset_step_index();
+        assert!(relint(&project, &mut report, &rules, &changed, true, &cache).unwrap());
+        assert_eq!(undefined_steps(&report), 0);
+        assert_eq!(
+            affected_subdirs(&project, &rules, &changed, true),
+            vec![0, 3]
+        );
+
+        let changed = BTreeSet::from([PathBuf::from("/elsewhere/A.java")]);
+        assert!(!step_index_changed(
+            project.get_shared_step_index(),
+            &changed
+        ));
+        assert!(!relint(&project, &mut report, &rules, &changed, false, &cache).unwrap());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn next_changes_collects_a_burst_of_events() {
+        let (sender, receiver) = mpsc::channel();
+        for path in ["A.java", "B.java", "A.java"] {
+            let event =
+                Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from(path));
+            sender.send(Ok(event)).unwrap();
+        }
+        sender
+            .send(Ok(Event::new(EventKind::Access(
+                notify::event::AccessKind::Any,
+            ))
+            .add_path(PathBuf::from("C.java"))))
+            .unwrap();
+
+        assert_eq!(
+            next_changes(&receiver, Duration::from_millis(10)),
+            Some(BTreeSet::from([
+                PathBuf::from("A.java"),
+                PathBuf::from("B.java")
+            ]))
+        );
+        drop(sender);
+        assert_eq!(next_changes(&receiver, Duration::from_millis(10)), None);
+    }
+}